@@ -0,0 +1,952 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::{Arc, RwLock};
+use tokio::sync::watch;
+
+use crate::catalog_store;
+use crate::config::load_config_or_default;
+use crate::details_cache::{cached_details, get_game_details};
+use crate::steamapi::GameDetails;
+use crate::token_index::{tokenize, TokenIndex};
+
+// Matches below this score are not considered the same game
+const MIN_MATCH_SCORE: f64 = 0.7;
+
+// Words marking apps that are not the base game, unless the query asks for them
+const NON_BASE_WORDS: &[&str] = &[
+    "soundtrack",
+    "ost",
+    "demo",
+    "dlc",
+    "playtest",
+    "artbook",
+    "dedicated",
+    "sdk",
+    "trailer",
+    "wallpaper",
+    "wallpapers",
+];
+
+// Score multiplier for apps that look like soundtracks, demos or DLC
+const NON_BASE_PENALTY: f64 = 0.7;
+
+// Normal quantile for the 95% confidence interval of the Wilson score
+const WILSON_Z: f64 = 1.96;
+
+/// The catalog every command searches. Readers take a cheap `Arc` snapshot so
+/// a new catalog can be swapped in without blocking them.
+static STEAM_CATALOG: Lazy<RwLock<Arc<SteamCatalog>>> =
+    Lazy::new(|| RwLock::new(Arc::new(SteamCatalog::default())));
+
+// Flipped once the first catalog is in place, the app window opens before that
+static CATALOG_LOADED: Lazy<watch::Sender<bool>> = Lazy::new(|| watch::channel(false).0);
+
+pub fn catalog() -> Arc<SteamCatalog> {
+    STEAM_CATALOG.read().unwrap().clone()
+}
+
+pub fn set_catalog(catalog: SteamCatalog) {
+    *STEAM_CATALOG.write().unwrap() = Arc::new(catalog);
+    CATALOG_LOADED.send_replace(true);
+}
+
+pub fn is_loaded() -> bool {
+    *CATALOG_LOADED.borrow()
+}
+
+/// Wait until startup has finished loading the catalog, for commands that
+/// would otherwise see an empty catalog right after launch
+pub async fn wait_until_loaded() {
+    let mut loaded = CATALOG_LOADED.subscribe();
+    // The sender lives in a static, so waiting can only end by loading
+    let _ = loaded.wait_for(|&loaded| loaded).await;
+}
+
+pub async fn loaded_catalog() -> Arc<SteamCatalog> {
+    wait_until_loaded().await;
+    catalog()
+}
+
+/// Appid for a game title. A manual match set with `set_steam_match` always
+/// wins over the catalog search.
+pub fn resolve_appid(title: &str) -> Option<u32> {
+    if let Some(&appid) = load_config_or_default().steam_matches.get(title) {
+        return Some(appid);
+    }
+    catalog().best_match(title).map(|entry| entry.appid)
+}
+
+/// Entry of `steam_games.json` and of the Steam app list
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SteamApp {
+    pub appid: u32,
+    pub name: String,
+}
+
+/// DLC and base game of an appid. Apps the catalog has no relations for are
+/// looked up through the store API.
+pub async fn related_apps(appid: u32) -> Result<RelatedApps, String> {
+    let catalog = loaded_catalog().await;
+    let mut entry = catalog.get(appid).cloned().unwrap_or(CatalogEntry {
+        appid,
+        ..Default::default()
+    });
+    if entry.app_type.is_none() {
+        entry.apply_details(&get_game_details(appid).await?);
+    }
+    Ok(catalog.related(&entry))
+}
+
+// Entry of `games_index.json`
+#[derive(Deserialize)]
+struct GameIndexEntry {
+    name: String,
+    header_image: String,
+    recommendations: u32,
+    positive: u32,
+    negative: u32,
+    // Only present in richer index files
+    #[serde(default)]
+    genres: Vec<String>,
+    #[serde(default)]
+    developers: Vec<String>,
+    #[serde(default)]
+    publishers: Vec<String>,
+    #[serde(default, rename = "type")]
+    app_type: Option<String>,
+    #[serde(default)]
+    dlc: Vec<u32>,
+    #[serde(default)]
+    fullgame: Option<u32>,
+}
+
+/// One app of the catalog, joined from both source files by appid
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CatalogEntry {
+    pub appid: u32,
+    pub name: String,
+    pub header_image: Option<String>,
+    pub recommendations: u32,
+    pub positive: u32,
+    pub negative: u32,
+    #[serde(default)]
+    pub genres: Vec<String>,
+    #[serde(default)]
+    pub developers: Vec<String>,
+    #[serde(default)]
+    pub publishers: Vec<String>,
+    // Store type ("game", "dlc", "music", ...), unknown until details are seen
+    #[serde(default)]
+    pub app_type: Option<String>,
+    // Base game of a DLC, soundtrack or demo
+    #[serde(default)]
+    pub parent: Option<u32>,
+    // DLC of a base game
+    #[serde(default)]
+    pub dlc: Vec<u32>,
+    #[serde(skip)]
+    pub(crate) normalized: String,
+}
+
+impl CatalogEntry {
+    // Only apps from the browse index carry images and review counts
+    fn is_indexed(&self) -> bool {
+        self.header_image.is_some()
+    }
+
+    /// Fill in what the browse index lacks from fetched appdetails
+    pub fn apply_details(&mut self, details: &GameDetails) {
+        if self.genres.is_empty() {
+            self.genres = details
+                .genres
+                .iter()
+                .flatten()
+                .filter_map(|g| g.description.clone())
+                .collect();
+        }
+        if self.developers.is_empty() {
+            self.developers = details.developers.clone().unwrap_or_default();
+        }
+        if self.publishers.is_empty() {
+            self.publishers = details.publishers.clone().unwrap_or_default();
+        }
+        if self.app_type.is_none() {
+            self.app_type = details.app_type.as_deref().map(str::to_lowercase);
+        }
+        if self.parent.is_none() {
+            self.parent = details
+                .fullgame
+                .as_ref()
+                .and_then(|game| game.appid.parse().ok())
+                .filter(|&parent| parent != self.appid);
+        }
+        if self.dlc.is_empty() {
+            self.dlc = details.dlc.clone().unwrap_or_default();
+        }
+    }
+
+    /// Word of `NON_BASE_WORDS` matching the store type, for apps that are
+    /// not a base game
+    fn non_base_type_word(&self) -> Option<&'static str> {
+        match self.app_type.as_deref()? {
+            "game" => None,
+            "music" => Some("soundtrack"),
+            "demo" => Some("demo"),
+            "video" | "advertising" => Some("trailer"),
+            "tool" | "config" => Some("sdk"),
+            _ => Some("dlc"),
+        }
+    }
+
+    fn facet_values(&self, kind: FacetKind) -> &[String] {
+        match kind {
+            FacetKind::Genre => &self.genres,
+            FacetKind::Developer => &self.developers,
+            FacetKind::Publisher => &self.publishers,
+        }
+    }
+
+    fn has_facet(&self, kind: FacetKind, value: &str) -> bool {
+        self.facet_values(kind)
+            .iter()
+            .any(|v| v.trim().eq_ignore_ascii_case(value.trim()))
+    }
+
+    fn total_reviews(&self) -> u32 {
+        self.positive.saturating_add(self.negative)
+    }
+
+    /// Lower bound of the Wilson score interval of the positive share, so a
+    /// game with a handful of reviews does not outrank one with thousands
+    fn wilson_score(&self) -> f64 {
+        let n = self.total_reviews() as f64;
+        if n == 0.0 {
+            return 0.0;
+        }
+        let p = self.positive as f64 / n;
+        let z2 = WILSON_Z * WILSON_Z;
+        let center = p + z2 / (2.0 * n);
+        let margin = WILSON_Z * ((p * (1.0 - p) + z2 / (4.0 * n)) / n).sqrt();
+        (center - margin) / (1.0 + z2 / n)
+    }
+}
+
+/// Game as returned to the browse pages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SteamGame {
+    pub appid: String,
+    pub name: String,
+    pub header_image: String,
+    pub recommendations: u32,
+    pub positive: u32,
+    pub negative: u32,
+}
+
+impl From<&CatalogEntry> for SteamGame {
+    fn from(entry: &CatalogEntry) -> Self {
+        Self {
+            appid: entry.appid.to_string(),
+            name: entry.name.clone(),
+            header_image: entry.header_image.clone().unwrap_or_default(),
+            recommendations: entry.recommendations,
+            positive: entry.positive,
+            negative: entry.negative,
+        }
+    }
+}
+
+/// Filters for the browse pages, unset fields match everything
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct BrowseFilter {
+    pub min_reviews: Option<u32>,
+    pub name: Option<String>,
+    pub genre: Option<String>,
+    pub developer: Option<String>,
+    pub publisher: Option<String>,
+    /// Hide DLC, soundtracks, demos and tools
+    pub base_only: bool,
+}
+
+impl BrowseFilter {
+    // `name` is the lowercased name filter
+    fn matches(&self, entry: &CatalogEntry, name: Option<&str>) -> bool {
+        let facets = [
+            (FacetKind::Genre, &self.genre),
+            (FacetKind::Developer, &self.developer),
+            (FacetKind::Publisher, &self.publisher),
+        ];
+        self.min_reviews
+            .map_or(true, |min| entry.total_reviews() >= min)
+            && name.map_or(true, |n| entry.name.to_lowercase().contains(n))
+            && facets
+                .iter()
+                .all(|(kind, value)| value.as_deref().map_or(true, |v| entry.has_facet(*kind, v)))
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum FacetKind {
+    Genre,
+    Developer,
+    Publisher,
+}
+
+/// A genre, developer or publisher with the number of browsable games
+#[derive(Serialize, Debug, Clone)]
+pub struct FacetCount {
+    pub name: String,
+    pub count: usize,
+}
+
+/// One page of browse results with the number of games across all pages
+#[derive(Serialize, Debug, Clone)]
+pub struct BrowsePage {
+    pub games: Vec<SteamGame>,
+    pub total: usize,
+}
+
+/// An app linked to another one as its DLC or base game
+#[derive(Serialize, Debug, Clone)]
+pub struct RelatedApp {
+    pub appid: u32,
+    // `None` for apps missing from the catalog
+    pub name: Option<String>,
+    pub app_type: Option<String>,
+    pub header_image: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RelatedApps {
+    pub appid: u32,
+    pub app_type: Option<String>,
+    pub parent: Option<RelatedApp>,
+    pub dlc: Vec<RelatedApp>,
+}
+
+/// What a catalog refresh changed
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct CatalogDiff {
+    pub added: usize,
+    pub removed: usize,
+    pub renamed: usize,
+    pub total: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CatalogMatch {
+    pub appid: u32,
+    pub name: String,
+    pub header_image: Option<String>,
+    pub score: f64,
+}
+
+#[derive(Default)]
+pub struct SteamCatalog {
+    entries: Vec<CatalogEntry>,
+    by_appid: HashMap<u32, usize>,
+    // Normalized title to entry, the most recommended app wins on collisions
+    exact: HashMap<String, usize>,
+    tokens: TokenIndex,
+    // Index into `NON_BASE_WORDS` for titles that contain one of them
+    non_base: Vec<Option<usize>>,
+    sorted_recommended: Vec<usize>,
+    sorted_reviewed: Vec<usize>,
+    sorted_review_count: Vec<usize>,
+    sorted_alphabetical: Vec<usize>,
+    facets: HashMap<FacetKind, Vec<FacetCount>>,
+}
+
+impl SteamCatalog {
+    /// Load `steam_games.json` (every app) and `games_index.json` (browse data)
+    /// joined by appid.
+    ///
+    /// The joined entries and their search index are compiled to a binary
+    /// catalog on the first run and read from there until either JSON file
+    /// changes.
+    pub fn load(
+        steam_games_path: &str,
+        games_index_path: &str,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let (mut entries, tokens) = match catalog_store::read(steam_games_path, games_index_path) {
+            Some(stored) => (stored.entries, stored.tokens),
+            None => {
+                let mut entries = join_sources(steam_games_path, games_index_path)?;
+                entries.sort_by_key(|e| e.appid);
+                let tokens = TokenIndex::build(entries.iter().map(|e| e.name.as_str()));
+                catalog_store::write(steam_games_path, games_index_path, &entries, &tokens);
+                (entries, tokens)
+            }
+        };
+
+        // Fill in genres, studios and app relations from fetched appdetails
+        let by_appid: HashMap<u32, usize> = entries
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (e.appid, i))
+            .collect();
+        for (appid, details) in cached_details() {
+            if let Some(&i) = by_appid.get(&appid) {
+                entries[i].apply_details(&details);
+            }
+        }
+
+        // Details never rename an app, so the stored index still matches
+        Ok(Self::from_indexed(entries, tokens))
+    }
+
+    pub fn from_entries(mut entries: Vec<CatalogEntry>) -> Self {
+        entries.sort_by_key(|e| e.appid);
+        let tokens = TokenIndex::build(entries.iter().map(|e| e.name.as_str()));
+        Self::from_indexed(entries, tokens)
+    }
+
+    // `entries` are sorted by appid and `tokens` was built from their names
+    // in that order
+    fn from_indexed(mut entries: Vec<CatalogEntry>, tokens: TokenIndex) -> Self {
+        for entry in &mut entries {
+            entry.normalized = normalize_title(&entry.name);
+        }
+
+        let by_appid = entries
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (e.appid, i))
+            .collect();
+
+        let mut exact: HashMap<String, usize> = HashMap::new();
+        for (i, entry) in entries.iter().enumerate() {
+            if entry.normalized.is_empty() {
+                continue;
+            }
+            match exact.get(&entry.normalized) {
+                Some(&other) if entries[other].recommendations >= entry.recommendations => {}
+                _ => {
+                    exact.insert(entry.normalized.clone(), i);
+                }
+            }
+        }
+
+        let non_base = entries
+            .iter()
+            .map(|e| {
+                tokenize(&e.name)
+                    .iter()
+                    .map(String::as_str)
+                    .chain(e.non_base_type_word())
+                    .find_map(|word| NON_BASE_WORDS.iter().position(|w| *w == word))
+            })
+            .collect();
+
+        // DLC that name their base game but are missing from its DLC list
+        let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+        for entry in &entries {
+            if let Some(parent) = entry.parent {
+                children.entry(parent).or_default().push(entry.appid);
+            }
+        }
+        for entry in &mut entries {
+            for child in children.remove(&entry.appid).unwrap_or_default() {
+                if !entry.dlc.contains(&child) {
+                    entry.dlc.push(child);
+                }
+            }
+        }
+
+        let mut indexed: Vec<usize> = (0..entries.len())
+            .filter(|&i| entries[i].is_indexed())
+            .collect();
+
+        // Sort by recommendations
+        indexed.sort_by(|&a, &b| entries[b].recommendations.cmp(&entries[a].recommendations));
+        let sorted_recommended = indexed.clone();
+
+        // Sort by review count
+        indexed.sort_by(|&a, &b| entries[b].total_reviews().cmp(&entries[a].total_reviews()));
+        let sorted_review_count = indexed.clone();
+
+        // Sort by name, case-insensitive
+        let mut names: Vec<(String, usize)> = indexed
+            .iter()
+            .map(|&i| (entries[i].name.to_lowercase(), i))
+            .collect();
+        names.sort();
+        let sorted_alphabetical = names.into_iter().map(|(_, i)| i).collect();
+
+        // Sort by review score (Wilson lower bound), ties go to more reviews
+        let scores: Vec<f64> = entries.iter().map(|e| e.wilson_score()).collect();
+        indexed.sort_by(|&a, &b| scores[b].partial_cmp(&scores[a]).unwrap_or(Ordering::Equal));
+        let sorted_reviewed = indexed;
+
+        let facets = [FacetKind::Genre, FacetKind::Developer, FacetKind::Publisher]
+            .into_iter()
+            .map(|kind| (kind, count_facet(&entries, &sorted_reviewed, kind)))
+            .collect();
+
+        Self {
+            entries,
+            by_appid,
+            exact,
+            tokens,
+            non_base,
+            sorted_recommended,
+            sorted_reviewed,
+            sorted_review_count,
+            sorted_alphabetical,
+            facets,
+        }
+    }
+
+    /// A new catalog with the app list replaced by `apps`.
+    ///
+    /// Browse data of known appids is kept. Games from the browse index stay
+    /// even when the list no longer has them, other missing apps are dropped.
+    pub fn with_apps(&self, apps: &[SteamApp]) -> (Self, CatalogDiff) {
+        let mut diff = CatalogDiff::default();
+        let mut seen = HashSet::new();
+        let mut entries = Vec::with_capacity(apps.len());
+
+        for app in apps {
+            let name = app.name.trim();
+            if name.is_empty() || !seen.insert(app.appid) {
+                continue;
+            }
+            match self.get(app.appid) {
+                Some(existing) => {
+                    let mut entry = existing.clone();
+                    if entry.name != name {
+                        entry.name = name.to_string();
+                        diff.renamed += 1;
+                    }
+                    entries.push(entry);
+                }
+                None => {
+                    diff.added += 1;
+                    entries.push(CatalogEntry {
+                        appid: app.appid,
+                        name: name.to_string(),
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+
+        for entry in &self.entries {
+            if seen.contains(&entry.appid) {
+                continue;
+            }
+            if entry.is_indexed() {
+                entries.push(entry.clone());
+            } else {
+                diff.removed += 1;
+            }
+        }
+
+        diff.total = entries.len();
+        (Self::from_entries(entries), diff)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn get(&self, appid: u32) -> Option<&CatalogEntry> {
+        self.by_appid.get(&appid).map(|&i| &self.entries[i])
+    }
+
+    /// Candidates for a title, best first.
+    ///
+    /// Titles are matched word by word through the token index, so words can
+    /// be in any order, abbreviated to a prefix or contain small typos. Exact
+    /// title matches always score 1.0. Soundtracks, demos and DLC are ranked
+    /// down unless the query mentions them, and ties go to the more
+    /// recommended app.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<CatalogMatch> {
+        let normalized = normalize_title(query);
+        if normalized.is_empty() || limit == 0 {
+            return Vec::new();
+        }
+
+        let query_words = tokenize(query);
+        let exact = self.exact.get(&normalized).copied();
+
+        let mut scored: Vec<(f64, usize)> = self
+            .tokens
+            .score(query)
+            .into_iter()
+            .map(|(i, score)| {
+                let i = i as usize;
+                let score = if exact == Some(i) {
+                    1.0
+                } else {
+                    score.min(0.99)
+                };
+                (score * self.non_base_penalty(i, &query_words), i)
+            })
+            .collect();
+
+        scored.sort_by(|(score_a, a), (score_b, b)| {
+            score_b
+                .partial_cmp(score_a)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| {
+                    self.entries[*b]
+                        .recommendations
+                        .cmp(&self.entries[*a].recommendations)
+                })
+        });
+
+        scored
+            .into_iter()
+            .take(limit)
+            .map(|(score, i)| {
+                let entry = &self.entries[i];
+                CatalogMatch {
+                    appid: entry.appid,
+                    name: entry.name.clone(),
+                    header_image: entry.header_image.clone(),
+                    score,
+                }
+            })
+            .collect()
+    }
+
+    fn non_base_penalty(&self, i: usize, query_words: &[String]) -> f64 {
+        match self.non_base[i] {
+            Some(word) if !query_words.iter().any(|w| w == NON_BASE_WORDS[word]) => {
+                NON_BASE_PENALTY
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// The single best match for a title, used wherever a game has to be
+    /// resolved to an appid
+    pub fn best_match(&self, query: &str) -> Option<&CatalogEntry> {
+        let best = self.search(query, 1).into_iter().next()?;
+        if best.score < MIN_MATCH_SCORE {
+            return None;
+        }
+        self.get(best.appid)
+    }
+
+    /// A page of indexed games in the order of `category`: `most_recommended`,
+    /// `best_reviewed`, `most_reviewed` or `alphabetical`
+    pub fn browse(
+        &self,
+        category: &str,
+        filter: &BrowseFilter,
+        page: usize,
+        page_size: usize,
+    ) -> Result<BrowsePage, String> {
+        let sorted_list = match category {
+            "most_recommended" => &self.sorted_recommended,
+            "best_reviewed" => &self.sorted_reviewed,
+            "most_reviewed" => &self.sorted_review_count,
+            "alphabetical" => &self.sorted_alphabetical,
+            _ => return Err(format!("Unknown category: {}", category)),
+        };
+
+        let name = filter
+            .name
+            .as_deref()
+            .map(|n| n.trim().to_lowercase())
+            .filter(|n| !n.is_empty());
+        let matching = sorted_list
+            .iter()
+            .filter(|&&i| !filter.base_only || self.non_base[i].is_none())
+            .map(|&i| &self.entries[i])
+            .filter(|e| filter.matches(e, name.as_deref()));
+
+        let mut total = 0;
+        let mut games = Vec::new();
+        for (position, entry) in matching.enumerate() {
+            total += 1;
+            if position / page_size.max(1) == page && games.len() < page_size {
+                games.push(SteamGame::from(entry));
+            }
+        }
+
+        Ok(BrowsePage { games, total })
+    }
+
+    /// DLC of a base game and the base game of a DLC. `entry` may carry
+    /// relations from freshly fetched details that the catalog lacks.
+    pub fn related(&self, entry: &CatalogEntry) -> RelatedApps {
+        let related_app = |appid: u32| match self.get(appid) {
+            Some(app) => RelatedApp {
+                appid,
+                name: Some(app.name.clone()),
+                app_type: app.app_type.clone(),
+                header_image: app.header_image.clone(),
+            },
+            None => RelatedApp {
+                appid,
+                name: None,
+                app_type: None,
+                header_image: None,
+            },
+        };
+
+        let mut dlc = entry.dlc.clone();
+        if let Some(known) = self.get(entry.appid) {
+            dlc.extend(known.dlc.iter().filter(|appid| !entry.dlc.contains(appid)));
+        }
+
+        RelatedApps {
+            appid: entry.appid,
+            app_type: entry.app_type.clone(),
+            parent: entry.parent.map(related_app),
+            dlc: dlc.into_iter().map(related_app).collect(),
+        }
+    }
+
+    /// Genres, developers or publishers of the browsable games, most common
+    /// first
+    pub fn facets(&self, kind: FacetKind) -> &[FacetCount] {
+        self.facets
+            .get(&kind)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+}
+
+// Parse both JSON sources and join them by appid
+fn join_sources(
+    steam_games_path: &str,
+    games_index_path: &str,
+) -> Result<Vec<CatalogEntry>, Box<dyn std::error::Error>> {
+    let reader = BufReader::new(File::open(steam_games_path)?);
+    let apps: HashMap<String, SteamApp> = serde_json::from_reader(reader)?;
+
+    let reader = BufReader::new(File::open(games_index_path)?);
+    let index: HashMap<String, GameIndexEntry> = serde_json::from_reader(reader)?;
+
+    let mut entries: HashMap<u32, CatalogEntry> = apps
+        .into_values()
+        .map(|app| {
+            let entry = CatalogEntry {
+                appid: app.appid,
+                name: app.name,
+                ..Default::default()
+            };
+            (entry.appid, entry)
+        })
+        .collect();
+
+    for (appid, indexed) in index {
+        let Ok(appid) = appid.parse::<u32>() else {
+            continue;
+        };
+        let entry = entries.entry(appid).or_insert_with(|| CatalogEntry {
+            appid,
+            name: indexed.name.clone(),
+            ..Default::default()
+        });
+        if entry.name.is_empty() {
+            entry.name = indexed.name;
+        }
+        entry.header_image = Some(indexed.header_image);
+        entry.recommendations = indexed.recommendations;
+        entry.positive = indexed.positive;
+        entry.negative = indexed.negative;
+        entry.genres = indexed.genres;
+        entry.developers = indexed.developers;
+        entry.publishers = indexed.publishers;
+        entry.app_type = indexed.app_type.map(|t| t.to_lowercase());
+        entry.parent = indexed.fullgame;
+        entry.dlc = indexed.dlc;
+    }
+
+    Ok(entries.into_values().collect())
+}
+
+// Count games per facet value, values differing only in case are merged and
+// keep the first spelling seen
+fn count_facet(entries: &[CatalogEntry], indexed: &[usize], kind: FacetKind) -> Vec<FacetCount> {
+    let mut counts: HashMap<String, FacetCount> = HashMap::new();
+    for &i in indexed {
+        let mut seen: Vec<String> = Vec::new();
+        for value in entries[i].facet_values(kind) {
+            let value = value.trim();
+            let key = value.to_lowercase();
+            if value.is_empty() || seen.contains(&key) {
+                continue;
+            }
+            counts
+                .entry(key.clone())
+                .or_insert_with(|| FacetCount {
+                    name: value.to_string(),
+                    count: 0,
+                })
+                .count += 1;
+            seen.push(key);
+        }
+    }
+
+    let mut counts: Vec<FacetCount> = counts.into_values().collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    counts
+}
+
+// Helper function to normalize game titles for comparison
+pub fn normalize_title(title: &str) -> String {
+    title
+        .to_lowercase()
+        .replace(|c: char| !c.is_alphanumeric(), "")
+        .replace("onlinefix", "")
+        .replace("crack", "")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(appid: u32, name: &str, recommendations: u32) -> CatalogEntry {
+        CatalogEntry {
+            appid,
+            name: name.to_string(),
+            header_image: Some(format!("https://example.com/{}.jpg", appid)),
+            recommendations,
+            ..Default::default()
+        }
+    }
+
+    fn ranked(catalog: &SteamCatalog, query: &str) -> Vec<u32> {
+        catalog
+            .search(query, 10)
+            .into_iter()
+            .map(|m| m.appid)
+            .collect()
+    }
+
+    #[test]
+    fn exact_title_outranks_longer_titles() {
+        let catalog =
+            SteamCatalog::from_entries(vec![game(1, "Portal", 10), game(2, "Portal 2", 1000)]);
+
+        let matches = catalog.search("portal", 10);
+        assert_eq!(matches[0].appid, 1);
+        assert_eq!(matches[0].score, 1.0);
+        assert_eq!(matches[1].appid, 2);
+        assert!(matches[1].score < 0.99);
+    }
+
+    #[test]
+    fn whole_words_outrank_prefix_completions() {
+        let catalog =
+            SteamCatalog::from_entries(vec![game(1, "The Witcher", 1000), game(2, "Witch It", 10)]);
+
+        assert_eq!(ranked(&catalog, "witch"), vec![2, 1]);
+    }
+
+    #[test]
+    fn typos_are_matched_through_trigrams() {
+        let catalog = SteamCatalog::from_entries(vec![game(1, "Skyrim", 10), game(2, "Sky", 10)]);
+
+        assert_eq!(ranked(&catalog, "skyrin"), vec![1]);
+        assert_eq!(catalog.best_match("skyrin").map(|e| e.appid), Some(1));
+    }
+
+    #[test]
+    fn words_match_in_any_order() {
+        let catalog = SteamCatalog::from_entries(vec![game(1, "Doom Eternal", 10)]);
+
+        assert_eq!(catalog.best_match("eternal doom").map(|e| e.appid), Some(1));
+    }
+
+    #[test]
+    fn non_base_apps_rank_below_the_game_unless_asked_for() {
+        let catalog = SteamCatalog::from_entries(vec![
+            game(1, "Hades", 100),
+            game(2, "Hades Soundtrack", 5000),
+        ]);
+
+        assert_eq!(ranked(&catalog, "hades"), vec![1, 2]);
+        assert_eq!(ranked(&catalog, "hades soundtrack"), vec![2, 1]);
+    }
+
+    #[test]
+    fn ties_go_to_the_more_recommended_app() {
+        let catalog = SteamCatalog::from_entries(vec![
+            game(1, "Doom Eternal", 10),
+            game(2, "Doom Classic", 500),
+        ]);
+
+        let matches = catalog.search("doom", 10);
+        assert_eq!(matches[0].score, matches[1].score);
+        assert_eq!(
+            matches.iter().map(|m| m.appid).collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+    }
+
+    fn reviewed(appid: u32, name: &str, positive: u32, negative: u32) -> CatalogEntry {
+        CatalogEntry {
+            positive,
+            negative,
+            ..game(appid, name, 0)
+        }
+    }
+
+    fn browsed(catalog: &SteamCatalog, category: &str) -> Vec<String> {
+        catalog
+            .browse(category, &BrowseFilter::default(), 0, 10)
+            .unwrap()
+            .games
+            .into_iter()
+            .map(|g| g.appid)
+            .collect()
+    }
+
+    #[test]
+    fn wilson_score_weighs_review_count() {
+        assert_eq!(reviewed(1, "None", 0, 0).wilson_score(), 0.0);
+
+        let few = reviewed(1, "Few", 5, 0).wilson_score();
+        let many = reviewed(2, "Many", 950, 50).wilson_score();
+        assert!(few < many, "{} should rank below {}", few, many);
+
+        // Same share, more reviews is more certain
+        let small = reviewed(3, "Small", 9, 1).wilson_score();
+        let large = reviewed(4, "Large", 900, 100).wilson_score();
+        assert!(small < large);
+        assert!(large < 0.9 && large > 0.87);
+    }
+
+    #[test]
+    fn browse_categories_sort_indexed_games() {
+        let mut unindexed = reviewed(4, "Unindexed", 100_000, 0);
+        unindexed.header_image = None;
+        let catalog = SteamCatalog::from_entries(vec![
+            CatalogEntry {
+                recommendations: 100,
+                ..reviewed(1, "zulu", 9, 0)
+            },
+            CatalogEntry {
+                recommendations: 50,
+                ..reviewed(2, "Alpha", 900, 100)
+            },
+            CatalogEntry {
+                recommendations: 10,
+                ..reviewed(3, "bravo", 2000, 1000)
+            },
+            unindexed,
+        ]);
+
+        assert_eq!(browsed(&catalog, "most_recommended"), ["1", "2", "3"]);
+        assert_eq!(browsed(&catalog, "most_reviewed"), ["3", "2", "1"]);
+        assert_eq!(browsed(&catalog, "best_reviewed"), ["2", "1", "3"]);
+        assert_eq!(browsed(&catalog, "alphabetical"), ["2", "3", "1"]);
+        assert!(catalog
+            .browse("newest", &BrowseFilter::default(), 0, 10)
+            .is_err());
+    }
+}
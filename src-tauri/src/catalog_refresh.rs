@@ -0,0 +1,120 @@
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::catalog::{loaded_catalog, set_catalog, CatalogDiff, SteamApp};
+use crate::steam_client::STEAM_CLIENT;
+
+// Bundled app list, used until the first refresh
+const BUNDLED_STEAM_GAMES: &str = "steam_games.json";
+
+// Held while a refresh runs, a second one is refused instead of queued
+static REFRESH_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+#[derive(Deserialize)]
+struct AppList {
+    apps: Vec<SteamApp>,
+}
+
+// App list formats accepted from Steam and from user-supplied dumps
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AppListFile {
+    // `ISteamApps/GetAppList` response
+    Response { applist: AppList },
+    List(Vec<SteamApp>),
+    // Format of the bundled `steam_games.json`
+    Map(HashMap<String, SteamApp>),
+}
+
+impl AppListFile {
+    fn into_apps(self) -> Vec<SteamApp> {
+        match self {
+            AppListFile::Response { applist } => applist.apps,
+            AppListFile::List(apps) => apps,
+            AppListFile::Map(apps) => apps.into_values().collect(),
+        }
+    }
+}
+
+fn refreshed_path() -> PathBuf {
+    let base_dir = dirs::cache_dir().unwrap_or_else(|| PathBuf::from("."));
+    base_dir.join("PirateLand").join("steam_games.json")
+}
+
+/// App list the catalog is loaded from: the last refreshed list if there is
+/// one, the bundled file otherwise
+pub fn steam_games_path() -> String {
+    let refreshed = refreshed_path();
+    if refreshed.exists() {
+        refreshed.to_string_lossy().into_owned()
+    } else {
+        BUNDLED_STEAM_GAMES.to_string()
+    }
+}
+
+fn parse_app_list(content: &[u8]) -> Result<Vec<SteamApp>, String> {
+    let apps = serde_json::from_slice::<AppListFile>(content)
+        .map_err(|_| "Unrecognized app list format".to_string())?
+        .into_apps();
+    if apps.is_empty() {
+        return Err("The app list is empty".to_string());
+    }
+    Ok(apps)
+}
+
+// Store the list in the bundled format so the next start loads it
+fn write_app_list(apps: &[SteamApp]) -> Result<(), String> {
+    let by_appid: HashMap<String, &SteamApp> = apps
+        .iter()
+        .map(|app| (app.appid.to_string(), app))
+        .collect();
+    let content = serde_json::to_vec(&by_appid).map_err(|e| e.to_string())?;
+
+    let path = refreshed_path();
+    let parent_dir = path.parent().ok_or("Invalid app list path")?;
+    let tmp_path = path.with_extension("json.tmp");
+    fs::create_dir_all(parent_dir)
+        .and_then(|_| fs::write(&tmp_path, content))
+        .and_then(|_| fs::rename(&tmp_path, &path))
+        .map_err(|e| format!("Failed to save the app list: {}", e))
+}
+
+/// Replace the catalog's app list with the current one from Steam, or with
+/// the JSON dump at `source` for offline use.
+///
+/// The new catalog is built in the background and swapped in at once, so
+/// searches keep using the old one until the refresh is done.
+pub async fn refresh_catalog(source: Option<String>) -> Result<CatalogDiff, String> {
+    let _running = REFRESH_LOCK
+        .try_lock()
+        .map_err(|_| "A catalog refresh is already running".to_string())?;
+
+    let content = match &source {
+        Some(path) => tokio::fs::read(path)
+            .await
+            .map_err(|e| format!("Failed to read {}: {}", path, e))?,
+        None => Vec::from(STEAM_CLIENT.app_list().await?),
+    };
+    let current = loaded_catalog().await;
+
+    tokio::task::spawn_blocking(move || {
+        let apps = parse_app_list(&content)?;
+        let (refreshed, diff) = current.with_apps(&apps);
+        write_app_list(&apps)?;
+        set_catalog(refreshed);
+
+        log::info!(
+            "[CATALOG] Refreshed: {} added, {} removed, {} renamed, {} total",
+            diff.added,
+            diff.removed,
+            diff.renamed,
+            diff.total
+        );
+        Ok(diff)
+    })
+    .await
+    .map_err(|e| format!("Catalog refresh failed: {}", e))?
+}
@@ -0,0 +1,449 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+use crate::catalog::CatalogEntry;
+use crate::token_index::TokenIndex;
+
+// Bumped whenever the layout below changes, older files are rebuilt
+const FORMAT_VERSION: u32 = 3;
+const MAGIC: &[u8; 4] = b"PLCT";
+
+// Size and modification time of a source file, the compiled catalog is only
+// used while both sources still match what it was built from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct SourceStamp {
+    len: u64,
+    modified_nanos: u64,
+}
+
+impl SourceStamp {
+    fn of(path: &str) -> io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        let modified_nanos = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0);
+        Ok(Self {
+            len: metadata.len(),
+            modified_nanos,
+        })
+    }
+}
+
+fn store_path() -> PathBuf {
+    let base_dir = dirs::cache_dir().unwrap_or_else(|| PathBuf::from("."));
+    base_dir.join("PirateLand").join("catalog.bin")
+}
+
+/// Catalog entries sorted by appid with the search index over their names
+pub struct StoredCatalog {
+    pub entries: Vec<CatalogEntry>,
+    pub tokens: TokenIndex,
+}
+
+/// Catalog compiled from `steam_games.json` and `games_index.json`, or `None`
+/// when there is no compiled catalog yet or a source has changed since it was
+/// written
+pub fn read(steam_games_path: &str, games_index_path: &str) -> Option<StoredCatalog> {
+    let stamps = [
+        SourceStamp::of(steam_games_path).ok()?,
+        SourceStamp::of(games_index_path).ok()?,
+    ];
+    let path = store_path();
+    let mut reader = BufReader::new(File::open(&path).ok()?);
+
+    match decode(&mut reader, &stamps) {
+        Ok(stored) => stored,
+        Err(e) => {
+            log::warn!("[CATALOG] Ignoring unreadable {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Compile the joined entries, sorted by appid, and the search index built
+/// from them so the next start can skip both the JSON sources and indexing.
+/// Failures are only logged, the JSON stays the source of truth.
+pub fn write(
+    steam_games_path: &str,
+    games_index_path: &str,
+    entries: &[CatalogEntry],
+    tokens: &TokenIndex,
+) {
+    let result = (|| -> io::Result<()> {
+        let stamps = [
+            SourceStamp::of(steam_games_path)?,
+            SourceStamp::of(games_index_path)?,
+        ];
+        let path = store_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Write next to the target and rename, so a crash never leaves a
+        // truncated catalog behind
+        let tmp_path = path.with_extension("bin.tmp");
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        encode(&mut writer, &stamps, entries, tokens)?;
+        writer
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()?;
+        fs::rename(&tmp_path, &path)
+    })();
+
+    if let Err(e) = result {
+        log::warn!("[CATALOG] Failed to write compiled catalog: {}", e);
+    }
+}
+
+// Layout, all integers little endian:
+//   magic, version, two source stamps, entry count, entries sorted by appid,
+//   vocabulary words with the entries using them, word count per entry,
+//   trigrams with the vocabulary words containing them
+// Strings are a u32 byte length followed by UTF-8, lists a u32 count followed
+// by their items. Missing strings and appids are stored as "" and 0.
+fn encode(
+    w: &mut impl Write,
+    stamps: &[SourceStamp; 2],
+    entries: &[CatalogEntry],
+    tokens: &TokenIndex,
+) -> io::Result<()> {
+    w.write_all(MAGIC)?;
+    write_u32(w, FORMAT_VERSION)?;
+    for stamp in stamps {
+        write_u64(w, stamp.len)?;
+        write_u64(w, stamp.modified_nanos)?;
+    }
+
+    debug_assert!(entries
+        .windows(2)
+        .all(|pair| pair[0].appid <= pair[1].appid));
+    write_u32(w, entries.len() as u32)?;
+
+    for entry in entries {
+        write_u32(w, entry.appid)?;
+        write_str(w, &entry.name)?;
+        match &entry.header_image {
+            Some(image) => {
+                w.write_all(&[1])?;
+                write_str(w, image)?;
+            }
+            None => w.write_all(&[0])?,
+        }
+        write_u32(w, entry.recommendations)?;
+        write_u32(w, entry.positive)?;
+        write_u32(w, entry.negative)?;
+        write_list(w, &entry.genres)?;
+        write_list(w, &entry.developers)?;
+        write_list(w, &entry.publishers)?;
+        write_str(w, entry.app_type.as_deref().unwrap_or_default())?;
+        // 0 is never a real appid
+        write_u32(w, entry.parent.unwrap_or(0))?;
+        write_appids(w, &entry.dlc)?;
+    }
+
+    write_u32(w, tokens.vocab.len() as u32)?;
+    for (word, postings) in tokens.vocab.iter().zip(&tokens.postings) {
+        write_str(w, word)?;
+        write_appids(w, postings)?;
+    }
+
+    write_u32(w, tokens.token_counts.len() as u32)?;
+    for &count in &tokens.token_counts {
+        w.write_all(&count.to_le_bytes())?;
+    }
+
+    // Sorted so the same catalog always compiles to the same bytes
+    let mut trigrams: Vec<(&String, &Vec<u32>)> = tokens.trigrams.iter().collect();
+    trigrams.sort();
+    write_u32(w, trigrams.len() as u32)?;
+    for (trigram, words) in trigrams {
+        write_str(w, trigram)?;
+        write_appids(w, words)?;
+    }
+    Ok(())
+}
+
+// `Ok(None)` means the file is valid but outdated
+fn decode(r: &mut impl Read, stamps: &[SourceStamp; 2]) -> io::Result<Option<StoredCatalog>> {
+    let mut magic = [0u8; 4];
+    r.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid("not a compiled catalog"));
+    }
+    if read_u32(r)? != FORMAT_VERSION {
+        return Ok(None);
+    }
+    for stamp in stamps {
+        let stored = SourceStamp {
+            len: read_u64(r)?,
+            modified_nanos: read_u64(r)?,
+        };
+        if stored != *stamp {
+            return Ok(None);
+        }
+    }
+
+    let count = read_u32(r)? as usize;
+    let mut entries = Vec::with_capacity(count.min(1 << 20));
+    for _ in 0..count {
+        entries.push(CatalogEntry {
+            appid: read_u32(r)?,
+            name: read_str(r)?,
+            header_image: match read_u8(r)? {
+                0 => None,
+                _ => Some(read_str(r)?),
+            },
+            recommendations: read_u32(r)?,
+            positive: read_u32(r)?,
+            negative: read_u32(r)?,
+            genres: read_list(r)?,
+            developers: read_list(r)?,
+            publishers: read_list(r)?,
+            app_type: Some(read_str(r)?).filter(|t| !t.is_empty()),
+            parent: Some(read_u32(r)?).filter(|&appid| appid != 0),
+            dlc: read_appids(r)?,
+            ..Default::default()
+        });
+    }
+
+    let word_count = read_u32(r)? as usize;
+    let mut vocab = Vec::with_capacity(word_count.min(1 << 20));
+    let mut postings = Vec::with_capacity(word_count.min(1 << 20));
+    for _ in 0..word_count {
+        vocab.push(read_str(r)?);
+        postings.push(read_appids(r)?);
+    }
+
+    let count = read_u32(r)?;
+    let token_counts = (0..count)
+        .map(|_| read_u16(r))
+        .collect::<io::Result<Vec<u16>>>()?;
+
+    let trigram_count = read_u32(r)? as usize;
+    let mut trigrams = HashMap::with_capacity(trigram_count.min(1 << 20));
+    for _ in 0..trigram_count {
+        let trigram = read_str(r)?;
+        trigrams.insert(trigram, read_appids(r)?);
+    }
+
+    // Lookups index straight into these lists, so a corrupt file must not
+    // point past them
+    let in_range = |ids: &[u32], len: usize| ids.iter().all(|&id| (id as usize) < len);
+    if token_counts.len() != entries.len()
+        || !postings.iter().all(|ids| in_range(ids, entries.len()))
+        || !trigrams.values().all(|ids| in_range(ids, vocab.len()))
+    {
+        return Err(invalid("search index does not match the entries"));
+    }
+
+    Ok(Some(StoredCatalog {
+        entries,
+        tokens: TokenIndex {
+            vocab,
+            postings,
+            trigrams,
+            token_counts,
+        },
+    }))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn write_u32(w: &mut impl Write, value: u32) -> io::Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+fn write_u64(w: &mut impl Write, value: u64) -> io::Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+fn write_str(w: &mut impl Write, value: &str) -> io::Result<()> {
+    write_u32(w, value.len() as u32)?;
+    w.write_all(value.as_bytes())
+}
+
+fn write_list(w: &mut impl Write, values: &[String]) -> io::Result<()> {
+    write_u32(w, values.len() as u32)?;
+    values.iter().try_for_each(|v| write_str(w, v))
+}
+
+fn write_appids(w: &mut impl Write, appids: &[u32]) -> io::Result<()> {
+    write_u32(w, appids.len() as u32)?;
+    appids.iter().try_for_each(|&appid| write_u32(w, appid))
+}
+
+fn read_u8(r: &mut impl Read) -> io::Result<u8> {
+    let mut buf = [0u8; 1];
+    r.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u16(r: &mut impl Read) -> io::Result<u16> {
+    let mut buf = [0u8; 2];
+    r.read_exact(&mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+fn read_u32(r: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(r: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_str(r: &mut impl Read) -> io::Result<String> {
+    let len = read_u32(r)? as usize;
+    let mut buf = Vec::new();
+    // `take` keeps a corrupt length from allocating gigabytes up front
+    r.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    String::from_utf8(buf).map_err(|_| invalid("string is not UTF-8"))
+}
+
+fn read_list(r: &mut impl Read) -> io::Result<Vec<String>> {
+    let count = read_u32(r)?;
+    (0..count).map(|_| read_str(r)).collect()
+}
+
+fn read_appids(r: &mut impl Read) -> io::Result<Vec<u32>> {
+    let count = read_u32(r)?;
+    (0..count).map(|_| read_u32(r)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stamps() -> [SourceStamp; 2] {
+        [
+            SourceStamp {
+                len: 10,
+                modified_nanos: 20,
+            },
+            SourceStamp {
+                len: 30,
+                modified_nanos: 40,
+            },
+        ]
+    }
+
+    fn entry(appid: u32, name: &str, indexed: bool) -> CatalogEntry {
+        let entry = CatalogEntry {
+            appid,
+            name: name.to_string(),
+            ..Default::default()
+        };
+        if !indexed {
+            return entry;
+        }
+        CatalogEntry {
+            header_image: Some(format!("https://example.com/{}.jpg", appid)),
+            recommendations: 1200,
+            positive: 900,
+            negative: 100,
+            genres: vec!["Action".to_string(), "RPG".to_string()],
+            developers: vec!["Studio".to_string()],
+            app_type: Some("game".to_string()),
+            dlc: vec![323140, 323141],
+            ..entry
+        }
+    }
+
+    fn encoded(entries: &[CatalogEntry]) -> Vec<u8> {
+        let tokens = TokenIndex::build(entries.iter().map(|e| e.name.as_str()));
+        let mut buf = Vec::new();
+        encode(&mut buf, &stamps(), entries, &tokens).unwrap();
+        buf
+    }
+
+    #[test]
+    fn round_trips_entries() {
+        let buf = encoded(&[
+            entry(10, "Counter-Strike", false),
+            entry(70, "Half-Life", true),
+        ]);
+
+        let decoded = decode(&mut buf.as_slice(), &stamps())
+            .unwrap()
+            .unwrap()
+            .entries;
+
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded[0].appid, 10);
+        assert_eq!(decoded[0].header_image, None);
+        assert_eq!(decoded[1].name, "Half-Life");
+        assert_eq!(decoded[1].positive, 900);
+        assert_eq!(decoded[1].genres, vec!["Action", "RPG"]);
+        assert_eq!(decoded[1].developers, vec!["Studio"]);
+        assert!(decoded[1].publishers.is_empty());
+        assert_eq!(decoded[1].app_type.as_deref(), Some("game"));
+        assert_eq!(decoded[1].dlc, vec![323140, 323141]);
+        assert_eq!(decoded[0].app_type, None);
+        assert_eq!(decoded[0].parent, None);
+    }
+
+    #[test]
+    fn round_trips_search_index() {
+        let entries = [
+            entry(10, "Counter-Strike", false),
+            entry(70, "Half-Life", true),
+            entry(220, "Half-Life 2", true),
+        ];
+        let built = TokenIndex::build(entries.iter().map(|e| e.name.as_str()));
+        let buf = encoded(&entries);
+
+        let tokens = decode(&mut buf.as_slice(), &stamps())
+            .unwrap()
+            .unwrap()
+            .tokens;
+
+        assert_eq!(tokens.vocab, built.vocab);
+        assert_eq!(tokens.postings, built.postings);
+        assert_eq!(tokens.trigrams, built.trigrams);
+        assert_eq!(tokens.token_counts, built.token_counts);
+    }
+
+    #[test]
+    fn index_pointing_past_the_entries_is_an_error() {
+        let entries = [entry(70, "Half-Life", true)];
+        let mut tokens = TokenIndex::build(entries.iter().map(|e| e.name.as_str()));
+        tokens.postings[0].push(5);
+        let mut buf = Vec::new();
+        encode(&mut buf, &stamps(), &entries, &tokens).unwrap();
+
+        assert!(decode(&mut buf.as_slice(), &stamps()).is_err());
+    }
+
+    #[test]
+    fn changed_source_invalidates_catalog() {
+        let buf = encoded(&[entry(70, "Half-Life", true)]);
+
+        let mut changed = stamps();
+        changed[1].modified_nanos += 1;
+
+        assert!(decode(&mut buf.as_slice(), &changed).unwrap().is_none());
+    }
+
+    #[test]
+    fn truncated_file_is_an_error() {
+        let mut buf = encoded(&[entry(70, "Half-Life", true)]);
+        buf.truncate(buf.len() - 3);
+
+        assert!(decode(&mut buf.as_slice(), &stamps()).is_err());
+    }
+}
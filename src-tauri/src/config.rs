@@ -1,88 +1,345 @@
 use std::fs::{self, File};
-use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
 use serde::{Serialize, Deserialize};
 use dirs::config_dir;
 
+use crate::steamapi::Locale;
+
 #[derive(Serialize,Deserialize, Clone)]
 pub struct RecentGameEntry{
     pub name: String,
     pub path: String,
 }
 
+// User-defined library data for one installed game, keyed by folder name
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct GameLibraryData {
+    pub favorite: bool,
+    pub tags: Vec<String>,
+    pub notes: String,
+    pub collections: Vec<String>,
+}
+
 #[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
 pub struct AppConfig {
     pub recent_games: Vec<RecentGameEntry>, // List of game titles
     pub defender_excluded: bool,
     pub game_images: std::collections::HashMap<String, String>,
+    pub save_locations: std::collections::HashMap<String, Vec<String>>, // Manual save folders per game
+    pub library_roots: Vec<String>, // Extra folders scanned for installed games
+    pub imported_games: Vec<String>, // Game folders imported from anywhere on disk
+    pub collections: Vec<String>, // Collection names in display order
+    pub library_data: std::collections::HashMap<String, GameLibraryData>,
+    pub steam_matches: std::collections::HashMap<String, u32>, // Manual title to appid overrides
+    pub store_locale: Locale, // Language and country of fetched store details
+    pub proxy_hosts: Vec<String>, // Extra image hosts the local proxy may fetch from
+    pub proxy_port: Option<u16>, // Fixed image proxy port, a free one is picked when unset
+}
+
+// Held across every read-modify-write, so concurrent updates cannot drop
+// each other's changes, and while a corrupt config is being replaced
+static SAVE_LOCK: Mutex<()> = Mutex::new(());
+
+// Previous configs kept next to the current one, newest first
+const BACKUP_COUNT: usize = 3;
+
+fn lock() -> MutexGuard<'static, ()> {
+    SAVE_LOCK.lock().unwrap_or_else(|e| e.into_inner())
 }
 
 // Get the configuration file path
-fn get_config_path() -> PathBuf {
+fn get_config_path() -> Result<PathBuf, String> {
     // Retrieve the base config directory (e.g., AppData\Roaming on Windows)
-    let base_dir = config_dir().expect("Failed to locate config directory");
+    let base_dir = config_dir().ok_or("Failed to locate config directory")?;
 
     // Append your app-specific directory and file
-    base_dir.join("PirateLand").join("config.json")
+    Ok(base_dir.join("PirateLand").join("config.json"))
 }
 
-// Load the configuration
-pub fn load_config() -> AppConfig {
-    let config_path = get_config_path();
-    if config_path.exists() {
-        let mut file = File::open(&config_path).expect("Failed to open config file");
-        let mut content = String::new();
-        file.read_to_string(&mut content).expect("Failed to read config file");
-        
-        // Handle legacy config format
-        let mut config: AppConfig = match serde_json::from_str(&content) {
-            Ok(c) => c,
-            Err(_) => {
-                // Try to parse as old format (Vec<String>)
-                let legacy: Vec<String> = serde_json::from_str(&content)
-                    .unwrap_or_default();
-                
-                AppConfig {
-                    recent_games: legacy.into_iter().map(|name| RecentGameEntry {
-                        name,
-                        path: String::new() // Set default path or handle differently
-                    }).collect(),
-                    ..AppConfig::default()
+// `config.json.bak` is the config before the last save, `config.json.bak.1`
+// the one before that and so on
+fn backup_path(config_path: &Path, generation: usize) -> PathBuf {
+    match generation {
+        0 => config_path.with_extension("json.bak"),
+        n => config_path.with_extension(format!("json.bak.{}", n)),
+    }
+}
+
+fn parse_config(content: &str) -> Option<AppConfig> {
+    if let Ok(config) = serde_json::from_str(content) {
+        return Some(config);
+    }
+
+    // Try to parse as old format (Vec<String>)
+    let legacy: Vec<String> = serde_json::from_str(content).ok()?;
+    Some(AppConfig {
+        recent_games: legacy.into_iter().map(|name| RecentGameEntry {
+            name,
+            path: String::new() // Set default path or handle differently
+        }).collect(),
+        ..AppConfig::default()
+    })
+}
+
+// Move an unparsable config aside so it can still be recovered by hand
+fn quarantine(config_path: &Path) -> Result<PathBuf, String> {
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let target = config_path.with_extension(format!("json.corrupt-{}", stamp));
+    fs::rename(config_path, &target)
+        .map_err(|e| format!("Failed to move the corrupt config aside: {}", e))?;
+    Ok(target)
+}
+
+// Newest backup that can still be parsed
+fn read_backup(config_path: &Path) -> Option<AppConfig> {
+    (0..BACKUP_COUNT).find_map(|generation| {
+        fs::read_to_string(backup_path(config_path, generation))
+            .ok()
+            .and_then(|content| parse_config(&content))
+    })
+}
+
+// Load the configuration. A config that cannot be parsed is quarantined and
+// replaced by the newest usable backup, or by defaults when there is none.
+pub fn load_config() -> Result<AppConfig, String> {
+    let config_path = get_config_path()?;
+    let _guard = lock();
+    load_from(&config_path)
+}
+
+fn load_from(config_path: &Path) -> Result<AppConfig, String> {
+    if !config_path.exists() {
+        return Ok(AppConfig::default());
+    }
+    let content = fs::read_to_string(config_path)
+        .map_err(|e| format!("Failed to read config file: {}", e))?;
+
+    let mut config = match parse_config(&content) {
+        Some(config) => config,
+        None => {
+            let target = quarantine(config_path)?;
+            match read_backup(config_path) {
+                Some(backup) => {
+                    log::warn!(
+                        "[CONFIG] Unreadable config moved to {}, restored the backup",
+                        target.display()
+                    );
+                    // Otherwise every later load would start from defaults
+                    write_to(config_path, &backup)?;
+                    backup
+                }
+                None => {
+                    log::warn!(
+                        "[CONFIG] Unreadable config moved to {}, starting from defaults",
+                        target.display()
+                    );
+                    AppConfig::default()
                 }
-            }
-        };
-        
-        // Migrate any empty paths if needed
-        for game in &mut config.recent_games {
-            if game.path.is_empty() {
-                game.path = "<unknown-path>".to_string();
             }
         }
-        
-        config
-    } else {
-        AppConfig::default()
+    };
+
+    // Migrate any empty paths if needed
+    for game in &mut config.recent_games {
+        if game.path.is_empty() {
+            game.path = "<unknown-path>".to_string();
+        }
     }
+
+    Ok(config)
 }
 
-pub fn save_game_image_to_config(game_name: &str, image_url: &str) {
-    let mut config = load_config();
-    config
-        .game_images
-        .insert(game_name.to_string(), image_url.to_string());
-    save_config(&config);
+// For lookups that can carry on without the user's settings, the error is
+// only logged
+pub fn load_config_or_default() -> AppConfig {
+    load_config().unwrap_or_else(|e| {
+        log::warn!("[CONFIG] {}", e);
+        AppConfig::default()
+    })
 }
 
-// Save the configuration
-pub fn save_config(config: &AppConfig) {
-    let config_path = get_config_path();
+/// Load the config, apply `change` and save it, all under one lock so
+/// concurrent updates cannot overwrite each other. Nothing is saved when
+/// `change` fails.
+pub fn update_config<T>(
+    change: impl FnOnce(&mut AppConfig) -> Result<T, String>,
+) -> Result<T, String> {
+    let config_path = get_config_path()?;
+    let _guard = lock();
+    let mut config = load_from(&config_path)?;
+    let result = change(&mut config)?;
+    write_to(&config_path, &config)?;
+    Ok(result)
+}
+
+pub fn save_game_image_to_config(game_name: &str, image_url: &str) -> Result<(), String> {
+    update_config(|config| {
+        config
+            .game_images
+            .insert(game_name.to_string(), image_url.to_string());
+        Ok(())
+    })
+}
+
+// Shift every backup one generation back, dropping the oldest, and copy the
+// current config in as the newest
+fn rotate_backups(config_path: &Path) -> std::io::Result<()> {
+    for generation in (1..BACKUP_COUNT).rev() {
+        let older = backup_path(config_path, generation - 1);
+        if older.exists() {
+            fs::rename(&older, backup_path(config_path, generation))?;
+        }
+    }
+    fs::copy(config_path, backup_path(config_path, 0)).map(|_| ())
+}
+
+// Write the configuration. It is written to a temporary file, flushed to
+// disk and renamed over the old one, which becomes the newest backup.
+fn write_to(config_path: &Path, config: &AppConfig) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(&config)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
 
     // Ensure the directory exists
-    if let Some(parent_dir) = config_path.parent() {
-        fs::create_dir_all(parent_dir).expect("Failed to create config directory");
+    let parent_dir = config_path.parent().ok_or("Invalid config path")?;
+    fs::create_dir_all(parent_dir)
+        .map_err(|e| format!("Failed to create config directory: {}", e))?;
+
+    let tmp_path = config_path.with_extension("json.tmp");
+    let written = File::create(&tmp_path).and_then(|mut file| {
+        file.write_all(content.as_bytes())?;
+        file.sync_all()
+    });
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(format!("Failed to write config file: {}", e));
+    }
+
+    if config_path.exists() {
+        if let Err(e) = rotate_backups(config_path) {
+            log::warn!("[CONFIG] Failed to back up the config: {}", e);
+        }
     }
+    fs::rename(&tmp_path, config_path)
+        .map_err(|e| format!("Failed to replace config file: {}", e))?;
+
+    // Persist the rename itself
+    #[cfg(unix)]
+    if let Ok(dir) = File::open(parent_dir) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
 
-    let mut file = File::create(&config_path).expect("Failed to create config file");
-    let content = serde_json::to_string_pretty(&config).expect("Failed to serialize config");
-    file.write_all(content.as_bytes()).expect("Failed to write config file");
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Fresh directory per test, tests run in parallel
+    fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("pirateland-config-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn config_with_port(port: u16) -> AppConfig {
+        AppConfig {
+            proxy_port: Some(port),
+            ..AppConfig::default()
+        }
+    }
+
+    fn saved_port(path: &Path) -> Option<u16> {
+        parse_config(&fs::read_to_string(path).unwrap())
+            .unwrap()
+            .proxy_port
+    }
+
+    fn files_containing(dir: &Path, pattern: &str) -> usize {
+        fs::read_dir(dir)
+            .unwrap()
+            .flatten()
+            .filter(|f| f.file_name().to_string_lossy().contains(pattern))
+            .count()
+    }
+
+    #[test]
+    fn writes_atomically_and_rotates_backups() {
+        let dir = test_dir("rotate");
+        let path = dir.join("config.json");
+
+        for port in 5000..5005 {
+            write_to(&path, &config_with_port(port)).unwrap();
+        }
+
+        assert_eq!(saved_port(&path), Some(5004));
+        assert_eq!(saved_port(&backup_path(&path, 0)), Some(5003));
+        assert_eq!(saved_port(&backup_path(&path, 1)), Some(5002));
+        assert_eq!(saved_port(&backup_path(&path, 2)), Some(5001));
+        assert!(!backup_path(&path, BACKUP_COUNT).exists());
+        assert_eq!(files_containing(&dir, ".tmp"), 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn restores_the_newest_readable_backup() {
+        let dir = test_dir("restore");
+        let path = dir.join("config.json");
+        write_to(&path, &config_with_port(5000)).unwrap();
+        write_to(&path, &config_with_port(5001)).unwrap();
+        write_to(&path, &config_with_port(5002)).unwrap();
+        fs::write(&path, "{ not json").unwrap();
+        fs::write(backup_path(&path, 0), "").unwrap();
+
+        let config = load_from(&path).unwrap();
+
+        assert_eq!(config.proxy_port, Some(5000));
+        // Written back, the next load does not depend on the backup
+        assert_eq!(saved_port(&path), Some(5000));
+        assert_eq!(files_containing(&dir, ".corrupt-"), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn quarantines_and_starts_over_without_backup() {
+        let dir = test_dir("quarantine");
+        let path = dir.join("config.json");
+        fs::write(&path, "{ not json").unwrap();
+
+        let config = load_from(&path).unwrap();
+
+        assert_eq!(config.proxy_port, None);
+        assert!(!path.exists());
+        let quarantined: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .flatten()
+            .map(|f| f.file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(quarantined.len(), 1);
+        assert!(quarantined[0].starts_with("config.json.corrupt-"));
+        assert_eq!(
+            fs::read_to_string(dir.join(&quarantined[0])).unwrap(),
+            "{ not json"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reads_the_legacy_format() {
+        let config = parse_config(r#"["Portal", "Hades"]"#).unwrap();
+        assert_eq!(config.recent_games.len(), 2);
+        assert_eq!(config.recent_games[1].name, "Hades");
+    }
 }
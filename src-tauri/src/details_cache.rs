@@ -0,0 +1,267 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::steamapi::{fetch_game_details, GameDetails, Locale};
+
+// Cached details younger than this are returned without touching the network
+const FRESH_TTL_SECS: u64 = 24 * 60 * 60;
+
+// Appids and locales that currently have a background refresh running
+static REFRESHING: Lazy<std::sync::Mutex<HashSet<(u32, Locale)>>> =
+    Lazy::new(|| std::sync::Mutex::new(HashSet::new()));
+
+#[derive(Serialize, Deserialize)]
+struct CachedDetails {
+    fetched_at: u64,
+    details: GameDetails,
+}
+
+#[derive(Serialize, Default)]
+pub struct DetailsCacheStats {
+    pub entries: usize,
+    pub stale_entries: usize,
+    pub total_bytes: u64,
+    pub oldest_fetched_at: Option<u64>,
+    pub newest_fetched_at: Option<u64>,
+}
+
+fn cache_dir() -> PathBuf {
+    let base_dir = dirs::cache_dir().unwrap_or_else(|| PathBuf::from("."));
+    base_dir.join("PirateLand").join("appdetails")
+}
+
+// Details in the default locale keep the plain `<appid>.json` name, the
+// catalog reads those to fill in genres and studios
+fn cache_path(appid: u32, locale: &Locale) -> PathBuf {
+    match locale.key() {
+        Some(key) => cache_dir().join(format!("{}_{}.json", appid, key)),
+        None => cache_dir().join(format!("{}.json", appid)),
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn read_cached(appid: u32, locale: &Locale) -> Option<CachedDetails> {
+    let content = fs::read_to_string(cache_path(appid, locale)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn write_cached(appid: u32, locale: &Locale, details: &GameDetails) {
+    let entry = CachedDetails {
+        fetched_at: now_secs(),
+        details: details.clone(),
+    };
+    let result = fs::create_dir_all(cache_dir()).and_then(|_| {
+        let content = serde_json::to_string(&entry)?;
+        fs::write(cache_path(appid, locale), content)
+    });
+    if let Err(e) = result {
+        log::warn!("[CACHE] Failed to cache details for {}: {}", appid, e);
+    }
+}
+
+// Fetch from the store API and store the result, errors are flattened to
+// strings so the future can be spawned
+async fn fetch_and_store(appid: u32, locale: &Locale) -> Result<GameDetails, String> {
+    match fetch_game_details(appid, locale).await {
+        Ok(details) => {
+            write_cached(appid, locale, &details);
+            Ok(details)
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn spawn_refresh(appid: u32, locale: &Locale) {
+    let key = (appid, locale.clone());
+    if !REFRESHING.lock().unwrap().insert(key.clone()) {
+        return;
+    }
+    tokio::spawn(async move {
+        if let Err(e) = fetch_and_store(appid, &key.1).await {
+            log::debug!("[CACHE] Background refresh of {} failed: {}", appid, e);
+        }
+        REFRESHING.lock().unwrap().remove(&key);
+    });
+}
+
+// Details in one locale, exactly as the store API returned them
+async fn get_localized_details(appid: u32, locale: &Locale) -> Result<GameDetails, String> {
+    if let Some(entry) = read_cached(appid, locale) {
+        if now_secs().saturating_sub(entry.fetched_at) >= FRESH_TTL_SECS {
+            spawn_refresh(appid, locale);
+        }
+        return Ok(entry.details);
+    }
+
+    fetch_and_store(appid, locale).await
+}
+
+/// Details for an appid in the language and country set in the config,
+/// served from the disk cache when possible.
+///
+/// Fresh entries are returned as is, stale ones are returned immediately while
+/// a refresh runs in the background. If the API cannot be reached (offline,
+/// rate limited) any cached entry is used regardless of its age. Text the
+/// store has no translation for is taken from the English details.
+pub async fn get_game_details(appid: u32) -> Result<GameDetails, String> {
+    let locale = Locale::from_config();
+    let mut details = get_localized_details(appid, &locale).await?;
+
+    if !locale.is_english() && details.has_missing_text() {
+        match get_localized_details(appid, &locale.english()).await {
+            Ok(english) => details.fill_missing(&english),
+            Err(e) => log::debug!("[CACHE] No English fallback for {}: {}", appid, e),
+        }
+    }
+
+    // Entries cached before requirements were parsed lack the fields
+    details.parse_requirements();
+    // The cache keeps Steam's HTML, only sanitized HTML reaches the frontend
+    details.sanitize_descriptions();
+    Ok(details)
+}
+
+pub fn cache_stats() -> DetailsCacheStats {
+    let mut stats = DetailsCacheStats::default();
+    let Ok(entries) = fs::read_dir(cache_dir()) else {
+        return stats;
+    };
+    let now = now_secs();
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let Ok(content) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        let Ok(cached) = serde_json::from_str::<CachedDetails>(&content) else {
+            continue;
+        };
+        stats.entries += 1;
+        stats.total_bytes += content.len() as u64;
+        if now.saturating_sub(cached.fetched_at) >= FRESH_TTL_SECS {
+            stats.stale_entries += 1;
+        }
+        stats.oldest_fetched_at = Some(
+            stats
+                .oldest_fetched_at
+                .map_or(cached.fetched_at, |t| t.min(cached.fetched_at)),
+        );
+        stats.newest_fetched_at = Some(
+            stats
+                .newest_fetched_at
+                .map_or(cached.fetched_at, |t| t.max(cached.fetched_at)),
+        );
+    }
+    stats
+}
+
+// Appid of a cache file name and whether it holds the default locale
+fn parse_cache_name(file_name: &str) -> Option<(u32, bool)> {
+    let stem = file_name.strip_suffix(".json")?;
+    match stem.split_once('_') {
+        Some((appid, _)) => Some((appid.parse().ok()?, false)),
+        None => Some((stem.parse().ok()?, true)),
+    }
+}
+
+/// Every cached appid regardless of its age, used to enrich the catalog. The
+/// default locale is preferred, any other locale stands in when it is missing.
+pub fn cached_details() -> Vec<(u32, GameDetails)> {
+    let Ok(entries) = fs::read_dir(cache_dir()) else {
+        return Vec::new();
+    };
+
+    let mut by_appid: HashMap<u32, (bool, GameDetails)> = HashMap::new();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let Some((appid, is_default)) = parse_cache_name(&entry.file_name().to_string_lossy())
+        else {
+            continue;
+        };
+        if by_appid.get(&appid).is_some_and(|(default, _)| *default) {
+            continue;
+        }
+        let Ok(content) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        if let Ok(cached) = serde_json::from_str::<CachedDetails>(&content) {
+            by_appid.insert(appid, (is_default, cached.details));
+        }
+    }
+
+    by_appid
+        .into_iter()
+        .map(|(appid, (_, details))| (appid, details))
+        .collect()
+}
+
+/// Remove one appid from the cache, or everything when `appid` is `None`
+pub fn clear_cache(appid: Option<u32>) -> std::io::Result<()> {
+    match appid {
+        Some(appid) => {
+            let Ok(entries) = fs::read_dir(cache_dir()) else {
+                return Ok(());
+            };
+            // Every locale of the appid
+            let default_name = format!("{}.json", appid);
+            let localized_prefix = format!("{}_", appid);
+            for entry in entries.filter_map(|e| e.ok()) {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if name == default_name || name.starts_with(&localized_prefix) {
+                    fs::remove_file(entry.path())?;
+                }
+            }
+            Ok(())
+        }
+        None => match fs::remove_dir_all(cache_dir()) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locale(language: Option<&str>, country_code: Option<&str>) -> Locale {
+        Locale {
+            language: language.map(str::to_string),
+            country_code: country_code.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn english_shares_the_default_cache_file() {
+        assert_eq!(locale(None, None).key(), None);
+        assert_eq!(locale(None, None).english().key(), None);
+        assert_eq!(
+            locale(Some("german"), Some("de"))
+                .english()
+                .key()
+                .as_deref(),
+            Some("-de")
+        );
+        assert_eq!(
+            locale(Some("german"), None).key().as_deref(),
+            Some("german-")
+        );
+    }
+
+    #[test]
+    fn parses_cache_names_of_every_locale() {
+        assert_eq!(parse_cache_name("440.json"), Some((440, true)));
+        assert_eq!(parse_cache_name("440_german-de.json"), Some((440, false)));
+        assert_eq!(parse_cache_name("440_-de.json"), Some((440, false)));
+        assert_eq!(parse_cache_name("stats.json"), None);
+        assert_eq!(parse_cache_name("440.json.tmp"), None);
+    }
+}
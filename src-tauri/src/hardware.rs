@@ -0,0 +1,156 @@
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+
+#[cfg(target_os = "windows")]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// Hardware of this machine, fields are `None` when they could not be read
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct Hardware {
+    pub os: String,
+    pub arch: String,
+    pub cpu_model: Option<String>,
+    pub cpu_cores: Option<u32>,
+    pub memory_mb: Option<u64>,
+    pub gpu: Option<String>,
+    pub free_disk_mb: Option<u64>,
+}
+
+/// Detect the local hardware, free disk space is measured where `games_dir`
+/// lives
+pub fn detect_hardware(games_dir: &Path) -> Hardware {
+    let mut hardware = Hardware {
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        ..Default::default()
+    };
+
+    if cfg!(target_os = "windows") {
+        hardware.cpu_model =
+            powershell("(Get-CimInstance Win32_Processor | Select-Object -First 1).Name");
+        hardware.cpu_cores =
+            powershell("(Get-CimInstance Win32_Processor | Measure-Object NumberOfCores -Sum).Sum")
+                .and_then(|v| v.parse().ok());
+        hardware.memory_mb =
+            powershell("(Get-CimInstance Win32_ComputerSystem).TotalPhysicalMemory")
+                .and_then(|v| v.parse::<u64>().ok())
+                .map(|bytes| bytes / (1024 * 1024));
+        hardware.gpu =
+            powershell("(Get-CimInstance Win32_VideoController | Select-Object -First 1).Name");
+        hardware.free_disk_mb = powershell(&format!(
+            "(Get-Item -LiteralPath '{}').PSDrive.Free",
+            existing_ancestor(games_dir)
+                .display()
+                .to_string()
+                .replace('\'', "''")
+        ))
+        .and_then(|v| v.parse::<u64>().ok())
+        .map(|bytes| bytes / (1024 * 1024));
+    } else {
+        let cpuinfo = fs::read_to_string("/proc/cpuinfo").unwrap_or_default();
+        hardware.cpu_model = cpuinfo_value(&cpuinfo, "model name");
+        hardware.cpu_cores = cpuinfo_value(&cpuinfo, "cpu cores").and_then(|v| v.parse().ok());
+        hardware.memory_mb = fs::read_to_string("/proc/meminfo")
+            .ok()
+            .and_then(|meminfo| meminfo_kb(&meminfo, "MemTotal"))
+            .map(|kb| kb / 1024);
+        hardware.gpu = lspci_gpu().or_else(sys_gpu);
+        hardware.free_disk_mb = df_free_mb(existing_ancestor(games_dir));
+    }
+
+    // Logical cores are still better than nothing
+    if hardware.cpu_cores.is_none() {
+        hardware.cpu_cores = std::thread::available_parallelism()
+            .ok()
+            .map(|n| n.get() as u32);
+    }
+    hardware
+}
+
+// The games folder may not exist yet, measure the closest folder that does
+fn existing_ancestor(path: &Path) -> &Path {
+    path.ancestors()
+        .find(|p| p.exists())
+        .unwrap_or(Path::new("."))
+}
+
+fn powershell(script: &str) -> Option<String> {
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-Command", script]);
+
+    // Do not flash a console window for every query
+    #[cfg(target_os = "windows")]
+    {
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    let output = command.output().ok()?;
+    let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !value.is_empty()).then_some(value)
+}
+
+fn cpuinfo_value(cpuinfo: &str, key: &str) -> Option<String> {
+    cpuinfo.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        (name.trim() == key).then(|| value.trim().to_string())
+    })
+}
+
+fn meminfo_kb(meminfo: &str, key: &str) -> Option<u64> {
+    meminfo.lines().find_map(|line| {
+        let value = line.strip_prefix(key)?.strip_prefix(':')?;
+        value.trim().trim_end_matches("kB").trim().parse().ok()
+    })
+}
+
+// First display controller reported by `lspci`, preferring a dedicated card
+fn lspci_gpu() -> Option<String> {
+    let output = Command::new("lspci").output().ok()?;
+    let listing = String::from_utf8_lossy(&output.stdout);
+    let gpus: Vec<&str> = listing
+        .lines()
+        .filter(|line| {
+            line.contains("VGA compatible controller")
+                || line.contains("3D controller")
+                || line.contains("Display controller")
+        })
+        .filter_map(|line| line.splitn(3, ':').nth(2))
+        .map(str::trim)
+        .collect();
+
+    gpus.iter()
+        .find(|gpu| !gpu.contains("Intel"))
+        .or(gpus.first())
+        .map(|gpu| gpu.to_string())
+}
+
+// Without `lspci` only the kernel driver of the first card is known
+fn sys_gpu() -> Option<String> {
+    let entries = fs::read_dir("/sys/class/drm").ok()?;
+    entries.filter_map(|e| e.ok()).find_map(|entry| {
+        let uevent = fs::read_to_string(entry.path().join("device").join("uevent")).ok()?;
+        uevent
+            .lines()
+            .find_map(|line| line.strip_prefix("DRIVER="))
+            .map(|driver| format!("{} driver", driver))
+    })
+}
+
+fn df_free_mb(path: &Path) -> Option<u64> {
+    let output = Command::new("df").arg("-Pk").arg(path).output().ok()?;
+    let listing = String::from_utf8_lossy(&output.stdout);
+    // Filesystem, 1024-blocks, Used, Available, Capacity, Mounted on
+    let available_kb: u64 = listing
+        .lines()
+        .nth(1)?
+        .split_whitespace()
+        .nth(3)?
+        .parse()
+        .ok()?;
+    Some(available_kb / 1024)
+}
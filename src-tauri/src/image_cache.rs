@@ -0,0 +1,743 @@
+use bytes::Bytes;
+use once_cell::sync::Lazy;
+use reqwest::header::{self, HeaderMap};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+
+use crate::proxy_guard;
+use crate::thumbnails::{self, ResizeOptions};
+
+// Least recently used images are evicted beyond this size
+const MAX_CACHE_BYTES: u64 = 512 * 1024 * 1024;
+
+// Larger bodies, and anything but images, stream through uncached
+const MAX_CACHED_BODY: usize = 16 * 1024 * 1024;
+
+// Prefetched images skip the limit above but not this one
+const MAX_STORED_BODY: u64 = 64 * 1024 * 1024;
+
+// Inserts within this long of each other are saved to the index together
+const SAVE_DELAY: Duration = Duration::from_secs(2);
+
+// Freshness of responses that do not set a max-age
+const DEFAULT_MAX_AGE_SECS: u64 = 7 * 24 * 60 * 60;
+
+const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36";
+
+// Some image hosts refuse hotlinking without it
+const REFERER: &str = "https://online-fix.me";
+
+static IMAGE_CACHE: Lazy<ImageCache> = Lazy::new(ImageCache::open);
+
+// Numbers the temporary files of concurrent downloads
+static NEXT_DOWNLOAD: AtomicU64 = AtomicU64::new(0);
+
+// One cached URL. Bodies are stored by content hash, so URLs serving the
+// same image share one file.
+#[derive(Serialize, Deserialize, Clone)]
+struct CacheEntry {
+    blob: String,
+    content_type: String,
+    size: u64,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    fetched_at: u64,
+    max_age: u64,
+    last_access: u64,
+}
+
+impl CacheEntry {
+    fn remaining_secs(&self, now: u64) -> u64 {
+        (self.fetched_at + self.max_age).saturating_sub(now)
+    }
+}
+
+/// An image ready to be served
+pub struct CachedImage {
+    pub body: Bytes,
+    pub content_type: String,
+    /// Content hash, usable as a strong `ETag`
+    pub etag: String,
+    /// Seconds the image stays fresh
+    pub max_age: u64,
+}
+
+/// Result of `fetch`: a cached image, or an origin response too large or not
+/// suited for the cache that is left to the caller to stream
+pub enum Fetched {
+    Cached(CachedImage),
+    Stream(OriginStream),
+}
+
+/// Origin response to stream. `head` is the start of the body, read before
+/// it turned out too large to cache, and goes out before the rest.
+pub struct OriginStream {
+    pub head: Bytes,
+    pub response: reqwest::Response,
+}
+
+impl From<reqwest::Response> for OriginStream {
+    fn from(response: reqwest::Response) -> Self {
+        Self {
+            head: Bytes::new(),
+            response,
+        }
+    }
+}
+
+// What the origin's `Cache-Control` allows
+struct CachePolicy {
+    no_store: bool,
+    max_age: u64,
+}
+
+impl CachePolicy {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let mut policy = Self {
+            no_store: false,
+            max_age: DEFAULT_MAX_AGE_SECS,
+        };
+        let Some(cache_control) = headers
+            .get(header::CACHE_CONTROL)
+            .and_then(|v| v.to_str().ok())
+        else {
+            return policy;
+        };
+
+        for directive in cache_control.split(',').map(|d| d.trim().to_lowercase()) {
+            if directive == "no-store" {
+                policy.no_store = true;
+            } else if directive == "no-cache" {
+                policy.max_age = 0;
+            } else if let Some(max_age) = directive.strip_prefix("max-age=") {
+                if let Ok(max_age) = max_age.trim_matches('"').parse() {
+                    policy.max_age = max_age;
+                }
+            }
+        }
+        policy
+    }
+}
+
+struct ImageCache {
+    client: Client,
+    index: Mutex<HashMap<String, CacheEntry>>,
+    // Files evicted since the last save, deleted with it
+    unused_blobs: Mutex<Vec<String>>,
+    save_scheduled: AtomicBool,
+    // Held while the index file is written, so snapshots land in order
+    save_lock: Mutex<()>,
+}
+
+fn cache_dir() -> PathBuf {
+    let base_dir = dirs::cache_dir().unwrap_or_else(|| PathBuf::from("."));
+    base_dir.join("PirateLand").join("images")
+}
+
+fn blob_path(blob: &str) -> PathBuf {
+    cache_dir().join("blobs").join(blob)
+}
+
+fn index_path() -> PathBuf {
+    cache_dir().join("index.json")
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl ImageCache {
+    fn open() -> Self {
+        let client = Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(Duration::from_secs(30))
+            .redirect(proxy_guard::redirect_policy())
+            .dns_resolver(Arc::new(proxy_guard::PublicResolver))
+            .build()
+            .unwrap_or_else(|_| Client::new());
+
+        // Entries whose file was removed behind our back are dropped
+        let index: HashMap<String, CacheEntry> = fs::read_to_string(index_path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        let index: HashMap<String, CacheEntry> = index
+            .into_iter()
+            .filter(|(_, entry)| blob_path(&entry.blob).exists())
+            .collect();
+        sweep_blobs(&index);
+
+        Self {
+            client,
+            index: Mutex::new(index),
+            unused_blobs: Mutex::new(Vec::new()),
+            save_scheduled: AtomicBool::new(false),
+            save_lock: Mutex::new(()),
+        }
+    }
+
+    // Cached entry for a URL, marking it as recently used
+    fn lookup(&self, url: &str, now: u64) -> Option<CacheEntry> {
+        let mut index = self.index.lock().unwrap();
+        let entry = index.get_mut(url)?;
+        entry.last_access = now;
+        Some(entry.clone())
+    }
+
+    // Only the in-memory index changes here, the disk work is left to a
+    // batched save off the request path
+    fn insert(&self, url: &str, entry: CacheEntry) {
+        let unused = {
+            let mut index = self.index.lock().unwrap();
+            index.insert(url.to_string(), entry);
+            evict(&mut index, MAX_CACHE_BYTES)
+        };
+        self.unused_blobs.lock().unwrap().extend(unused);
+        schedule_save();
+    }
+
+    // Blocking, runs on the blocking thread pool
+    fn save(&self) {
+        let _saving = self.save_lock.lock().unwrap();
+        let content = {
+            let index = self.index.lock().unwrap();
+            // An evicted image may have been stored again since, and a file
+            // deleted with the lock released could lose that new entry
+            let unused = std::mem::take(&mut *self.unused_blobs.lock().unwrap());
+            for blob in unused {
+                if !index.values().any(|entry| entry.blob == blob) {
+                    let _ = fs::remove_file(blob_path(&blob));
+                }
+            }
+            serde_json::to_string(&*index)
+        };
+
+        let path = index_path();
+        let tmp_path = path.with_extension("json.tmp");
+        let result = content
+            .map_err(std::io::Error::from)
+            .and_then(|content| fs::write(&tmp_path, content))
+            .and_then(|_| fs::rename(&tmp_path, &path));
+        if let Err(e) = result {
+            log::warn!("[IMAGES] Failed to save the cache index: {}", e);
+        }
+    }
+}
+
+// Save the index shortly, unless a save is already waiting. Entries inserted
+// right before the app quits may miss the file, their images are fetched
+// again and the orphaned files swept on the next start.
+fn schedule_save() {
+    let cache = &*IMAGE_CACHE;
+    if cache.save_scheduled.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    tokio::spawn(async move {
+        tokio::time::sleep(SAVE_DELAY).await;
+        // Inserts from here on need a save of their own
+        cache.save_scheduled.store(false, Ordering::SeqCst);
+        if let Err(e) = tokio::task::spawn_blocking(move || cache.save()).await {
+            log::warn!("[IMAGES] Failed to save the cache index: {}", e);
+        }
+    });
+}
+
+// Files left behind by a crash or by entries dropped while loading the index
+fn sweep_blobs(index: &HashMap<String, CacheEntry>) {
+    let Ok(files) = fs::read_dir(cache_dir().join("blobs")) else {
+        return;
+    };
+    let referenced: HashSet<&str> = index.values().map(|e| e.blob.as_str()).collect();
+    for file in files.flatten() {
+        let name = file.file_name();
+        if !referenced.contains(name.to_string_lossy().as_ref()) {
+            let _ = fs::remove_file(file.path());
+        }
+    }
+}
+
+// Drop least recently used entries until the stored files fit `max_bytes`,
+// returns the files no entry refers to anymore
+fn evict(index: &mut HashMap<String, CacheEntry>, max_bytes: u64) -> Vec<String> {
+    // Entries referring to every file, and the file's size
+    let mut blobs: HashMap<String, (usize, u64)> = HashMap::new();
+    for entry in index.values() {
+        blobs.entry(entry.blob.clone()).or_insert((0, entry.size)).0 += 1;
+    }
+    let mut total: u64 = blobs.values().map(|(_, size)| size).sum();
+    let mut unused = Vec::new();
+    if total <= max_bytes {
+        return unused;
+    }
+
+    let mut by_access: Vec<(u64, String)> = index
+        .iter()
+        .map(|(url, entry)| (entry.last_access, url.clone()))
+        .collect();
+    by_access.sort();
+
+    for (_, url) in by_access {
+        if total <= max_bytes {
+            break;
+        }
+        let Some(removed) = index.remove(&url) else {
+            continue;
+        };
+        let Some((refs, size)) = blobs.get_mut(&removed.blob) else {
+            continue;
+        };
+        *refs -= 1;
+        if *refs == 0 {
+            total = total.saturating_sub(*size);
+            unused.push(removed.blob);
+        }
+    }
+    unused
+}
+
+async fn read_blob(entry: &CacheEntry, now: u64) -> Option<CachedImage> {
+    let body = tokio::fs::read(blob_path(&entry.blob)).await.ok()?;
+    Some(CachedImage {
+        body: Bytes::from(body),
+        content_type: entry.content_type.clone(),
+        etag: format!("\"{}\"", entry.blob),
+        max_age: entry.remaining_secs(now),
+    })
+}
+
+async fn write_blob(blob: &str, body: &[u8]) -> std::io::Result<()> {
+    let path = blob_path(blob);
+    if path.exists() {
+        return Ok(());
+    }
+    tokio::fs::create_dir_all(cache_dir().join("blobs")).await?;
+    // Written under a temporary name so a crash never leaves half an image
+    let tmp_path = path.with_extension("part");
+    tokio::fs::write(&tmp_path, body).await?;
+    tokio::fs::rename(&tmp_path, &path).await
+}
+
+fn check_url(url: &str) -> Result<(), String> {
+    let url = reqwest::Url::parse(url).map_err(|e| format!("Invalid image URL: {}", e))?;
+    proxy_guard::check_url(&url)
+}
+
+// Offline or failing origins still get the last copy we have
+async fn stale_or(
+    cached: Option<&CacheEntry>,
+    now: u64,
+    error: String,
+) -> Result<CachedImage, String> {
+    match cached {
+        Some(entry) => read_blob(entry, now).await.ok_or(error),
+        None => Err(error),
+    }
+}
+
+// Origin response worth caching, with the entry to file its body under
+struct Download {
+    response: reqwest::Response,
+    // `blob` and `size` are only known once the body is read
+    entry: CacheEntry,
+    stale: Option<CacheEntry>,
+}
+
+enum Origin {
+    Cached(CachedImage),
+    Uncacheable(reqwest::Response),
+    Download(Download),
+}
+
+// The cached image while it is fresh or still valid, otherwise the origin
+// response to read
+async fn request(url: &str, now: u64) -> Result<Origin, String> {
+    check_url(url)?;
+
+    let cache = &*IMAGE_CACHE;
+    let cached = cache.lookup(url, now);
+
+    if let Some(entry) = cached.as_ref().filter(|e| e.remaining_secs(now) > 0) {
+        if let Some(image) = read_blob(entry, now).await {
+            return Ok(Origin::Cached(image));
+        }
+    }
+
+    let mut request = cache.client.get(url).header(header::REFERER, REFERER);
+    if let Some(entry) = &cached {
+        if let Some(etag) = &entry.etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &entry.last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => {
+            let error = format!("Failed to fetch image: {}", e);
+            return stale_or(cached.as_ref(), now, error)
+                .await
+                .map(Origin::Cached);
+        }
+    };
+    let policy = CachePolicy::from_headers(response.headers());
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+        if let Some(mut entry) = cached.clone() {
+            entry.fetched_at = now;
+            entry.max_age = policy.max_age;
+            if let Some(image) = read_blob(&entry, now).await {
+                cache.insert(url, entry);
+                return Ok(Origin::Cached(image));
+            }
+        }
+    }
+    if !response.status().is_success() {
+        let error = format!("Image origin returned {}", response.status());
+        return stale_or(cached.as_ref(), now, error)
+            .await
+            .map(Origin::Cached);
+    }
+
+    let header_value = |name: header::HeaderName| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+    };
+    let entry = CacheEntry {
+        blob: String::new(),
+        content_type: header_value(header::CONTENT_TYPE)
+            .unwrap_or_else(|| "application/octet-stream".into()),
+        size: 0,
+        etag: header_value(header::ETAG),
+        last_modified: header_value(header::LAST_MODIFIED),
+        fetched_at: now,
+        max_age: policy.max_age,
+        last_access: now,
+    };
+
+    if policy.no_store || !entry.content_type.starts_with("image/") {
+        return Ok(Origin::Uncacheable(response));
+    }
+    Ok(Origin::Download(Download {
+        response,
+        entry,
+        stale: cached,
+    }))
+}
+
+/// Image at `url`, from the disk cache while it is fresh.
+///
+/// Stale entries are revalidated with the origin's `ETag` or
+/// `Last-Modified`. When the origin cannot be reached any cached copy is
+/// served regardless of its age. Only URLs passing
+/// `proxy_guard::check_url` are fetched.
+pub async fn fetch(url: &str) -> Result<Fetched, String> {
+    let now = now_secs();
+    let Download {
+        mut response,
+        mut entry,
+        stale,
+    } = match request(url, now).await? {
+        Origin::Cached(image) => return Ok(Fetched::Cached(image)),
+        Origin::Uncacheable(response) => return Ok(Fetched::Stream(response.into())),
+        Origin::Download(download) => download,
+    };
+
+    if response
+        .content_length()
+        .is_some_and(|length| length > MAX_CACHED_BODY as u64)
+    {
+        return Ok(Fetched::Stream(response.into()));
+    }
+
+    // The length may be missing or wrong, so the limit is enforced while
+    // reading and whatever was read so far is streamed with the rest
+    let mut body = Vec::new();
+    loop {
+        match response.chunk().await {
+            Ok(Some(chunk)) => {
+                body.extend_from_slice(&chunk);
+                if body.len() > MAX_CACHED_BODY {
+                    return Ok(Fetched::Stream(OriginStream {
+                        head: Bytes::from(body),
+                        response,
+                    }));
+                }
+            }
+            Ok(None) => break,
+            Err(e) => {
+                let error = format!("Failed to read image data: {}", e);
+                return stale_or(stale.as_ref(), now, error)
+                    .await
+                    .map(Fetched::Cached);
+            }
+        }
+    }
+    let body = Bytes::from(body);
+    entry.blob = format!("{:x}", Sha256::digest(&body));
+    entry.size = body.len() as u64;
+
+    let image = CachedImage {
+        body,
+        content_type: entry.content_type.clone(),
+        etag: format!("\"{}\"", entry.blob),
+        max_age: entry.max_age,
+    };
+    match write_blob(&entry.blob, &image.body).await {
+        Ok(()) => IMAGE_CACHE.insert(url, entry),
+        Err(e) => log::warn!("[IMAGES] Failed to cache {}: {}", url, e),
+    }
+    Ok(Fetched::Cached(image))
+}
+
+/// Put the image at `url` in the disk cache without keeping it in memory,
+/// for images that are not on screen yet. Unlike `fetch` this also stores
+/// images larger than what is served from memory.
+pub async fn store(url: &str) -> Result<(), String> {
+    let now = now_secs();
+    let Download {
+        mut response,
+        mut entry,
+        ..
+    } = match request(url, now).await? {
+        Origin::Cached(_) => return Ok(()),
+        Origin::Uncacheable(_) => return Err("Not a cacheable image".to_string()),
+        Origin::Download(download) => download,
+    };
+    if response
+        .content_length()
+        .is_some_and(|length| length > MAX_STORED_BODY)
+    {
+        return Err("Image is too large to cache".to_string());
+    }
+
+    let blobs_dir = cache_dir().join("blobs");
+    tokio::fs::create_dir_all(&blobs_dir)
+        .await
+        .map_err(|e| format!("Failed to create the image cache: {}", e))?;
+    // Unique per download, the final name is only known once it is hashed
+    let tmp_path = blobs_dir.join(format!(
+        "{}-{}.part",
+        std::process::id(),
+        NEXT_DOWNLOAD.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let (blob, size) = match download_to(&mut response, &tmp_path).await {
+        Ok(downloaded) => downloaded,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&tmp_path).await;
+            return Err(e);
+        }
+    };
+    tokio::fs::rename(&tmp_path, blob_path(&blob))
+        .await
+        .map_err(|e| format!("Failed to cache {}: {}", url, e))?;
+
+    entry.blob = blob;
+    entry.size = size;
+    IMAGE_CACHE.insert(url, entry);
+    Ok(())
+}
+
+// Write the body to `path` chunk by chunk, returns its hash and size
+async fn download_to(
+    response: &mut reqwest::Response,
+    path: &Path,
+) -> Result<(String, u64), String> {
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .map_err(|e| format!("Failed to cache image: {}", e))?;
+    let mut hasher = Sha256::new();
+    let mut size: u64 = 0;
+
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("Failed to read image data: {}", e))?
+    {
+        size += chunk.len() as u64;
+        if size > MAX_STORED_BODY {
+            return Err("Image is too large to cache".to_string());
+        }
+        hasher.update(&chunk);
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| format!("Failed to cache image: {}", e))?;
+    }
+    file.flush()
+        .await
+        .map_err(|e| format!("Failed to cache image: {}", e))?;
+
+    Ok((format!("{:x}", hasher.finalize()), size))
+}
+
+/// `url` resized per `options`. Variants are cached next to the original and
+/// stay fresh as long as it does, images that are not resized (small,
+/// animated or unreadable) are served as they are.
+pub async fn fetch_resized(url: &str, options: &ResizeOptions) -> Result<Fetched, String> {
+    let cache = &*IMAGE_CACHE;
+    let key = format!("{}#{}", url, options.key());
+    let now = now_secs();
+
+    if let Some(entry) = cache
+        .lookup(&key, now)
+        .filter(|e| e.remaining_secs(now) > 0)
+    {
+        if let Some(image) = read_blob(&entry, now).await {
+            return Ok(Fetched::Cached(image));
+        }
+    }
+
+    let original = match fetch(url).await? {
+        Fetched::Cached(image) => image,
+        stream => return Ok(stream),
+    };
+    let body = original.body.clone();
+    let resize_options = *options;
+    let resized = tokio::task::spawn_blocking(move || thumbnails::resize(&body, &resize_options))
+        .await
+        .ok()
+        .flatten();
+
+    let (image, blob) = match resized {
+        Some((data, content_type)) => {
+            let blob = format!("{:x}", Sha256::digest(&data));
+            let image = CachedImage {
+                body: Bytes::from(data),
+                content_type: content_type.to_string(),
+                etag: format!("\"{}\"", blob),
+                max_age: original.max_age,
+            };
+            if let Err(e) = write_blob(&blob, &image.body).await {
+                log::warn!("[IMAGES] Failed to cache {}: {}", key, e);
+                return Ok(Fetched::Cached(image));
+            }
+            (image, blob)
+        }
+        // The variant shares the original's file, whose name is its ETag
+        None => {
+            let blob = original.etag.trim_matches('"').to_string();
+            (original, blob)
+        }
+    };
+
+    cache.insert(
+        &key,
+        CacheEntry {
+            blob,
+            content_type: image.content_type.clone(),
+            size: image.body.len() as u64,
+            etag: None,
+            last_modified: None,
+            fetched_at: now,
+            max_age: image.max_age,
+            last_access: now,
+        },
+    );
+    Ok(Fetched::Cached(image))
+}
+
+/// Part of `url` straight from the origin, the `Range` and `If-Range`
+/// request headers are forwarded as given
+pub async fn fetch_range(
+    url: &str,
+    range: &str,
+    if_range: Option<&str>,
+) -> Result<reqwest::Response, String> {
+    check_url(url)?;
+
+    let mut request = IMAGE_CACHE
+        .client
+        .get(url)
+        .header(header::REFERER, REFERER)
+        .header(header::RANGE, range);
+    if let Some(if_range) = if_range {
+        request = request.header(header::IF_RANGE, if_range);
+    }
+    request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch media: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn policy(cache_control: Option<&'static str>) -> CachePolicy {
+        let mut headers = HeaderMap::new();
+        if let Some(value) = cache_control {
+            headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(value));
+        }
+        CachePolicy::from_headers(&headers)
+    }
+
+    fn entry(blob: &str, size: u64, last_access: u64) -> CacheEntry {
+        CacheEntry {
+            blob: blob.to_string(),
+            content_type: "image/jpeg".to_string(),
+            size,
+            etag: None,
+            last_modified: None,
+            fetched_at: 0,
+            max_age: 0,
+            last_access,
+        }
+    }
+
+    #[test]
+    fn reads_cache_control() {
+        let default = policy(None);
+        assert!(!default.no_store);
+        assert_eq!(default.max_age, DEFAULT_MAX_AGE_SECS);
+
+        assert_eq!(policy(Some("public, max-age=3600")).max_age, 3600);
+        assert_eq!(policy(Some("Max-Age=\"60\"")).max_age, 60);
+        assert_eq!(policy(Some("no-cache")).max_age, 0);
+        assert!(policy(Some("private, no-store")).no_store);
+        assert_eq!(policy(Some("max-age=soon")).max_age, DEFAULT_MAX_AGE_SECS);
+    }
+
+    #[test]
+    fn evicts_least_recently_used_entries() {
+        let mut index = HashMap::from([
+            ("old".to_string(), entry("a", 40, 1)),
+            ("newer".to_string(), entry("b", 40, 2)),
+            ("newest".to_string(), entry("c", 40, 3)),
+        ]);
+
+        assert!(evict(&mut index, 120).is_empty());
+        assert_eq!(evict(&mut index, 100), vec!["a"]);
+        assert!(!index.contains_key("old"));
+        assert_eq!(index.len(), 2);
+    }
+
+    #[test]
+    fn shared_files_are_kept_while_referenced() {
+        let mut index = HashMap::from([
+            ("first".to_string(), entry("shared", 50, 1)),
+            ("resized".to_string(), entry("shared", 50, 2)),
+            ("other".to_string(), entry("other", 50, 3)),
+        ]);
+
+        // Dropping the first entry frees nothing, the variant still uses it
+        assert_eq!(evict(&mut index, 60), vec!["shared"]);
+        assert_eq!(index.keys().collect::<Vec<_>>(), vec!["other"]);
+    }
+}
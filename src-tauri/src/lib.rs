@@ -1,4 +1,8 @@
-use config::{load_config, save_config, save_game_image_to_config, RecentGameEntry};
+use catalog::{
+    catalog, loaded_catalog, set_catalog, BrowseFilter, BrowsePage, FacetCount, FacetKind,
+    SteamCatalog,
+};
+use config::{load_config, save_game_image_to_config, update_config, RecentGameEntry};
 use lazy_static::lazy_static;
 use once_cell::sync::Lazy;
 use regex::Regex;
@@ -17,10 +21,7 @@ use std::{
     process::{Command, Stdio},
     sync::Arc,
 };
-use steamapi::{
-    fetch_game_details, load_steam_games, GameDetails, SteamApp, SteamGame, SteamGameStore,
-    SteamGameStoreIndex,
-};
+use steamapi::GameDetails;
 use tauri::{Window, WindowEvent};
 use tokio::runtime::Runtime;
 use tokio::sync::Mutex;
@@ -31,21 +32,32 @@ static AUTH_CLIENT: once_cell::sync::Lazy<
     Arc<Mutex<Option<auth_and_download::AuthenticatedClient>>>,
 > = once_cell::sync::Lazy::new(|| Arc::new(Mutex::new(None)));
 
-static STEAM_GAME_STORE: once_cell::sync::Lazy<Arc<Mutex<SteamGameStore>>> =
-    once_cell::sync::Lazy::new(|| Arc::new(Mutex::new(SteamGameStore::new())));
 use torrent_manager::TorrentManager;
 
 static TORRENT_MANAGER: Lazy<Arc<Mutex<Option<Arc<TorrentManager>>>>> =
     Lazy::new(|| Arc::new(Mutex::new(None)));
-
-pub static STEAM_GAME_STORE_INDEX: Lazy<Arc<Mutex<SteamGameStoreIndex>>> =
-    Lazy::new(|| Arc::new(Mutex::new(SteamGameStoreIndex::new())));
 // The main lib file that is the main entry for the app
 mod auth_and_download;
+mod catalog;
+mod catalog_refresh;
+mod catalog_store;
 mod config;
+mod details_cache;
+mod hardware;
+mod image_cache;
+mod library;
+mod manifest;
+mod media;
 mod proxy;
+mod proxy_guard;
+mod requirements;
+mod rich_text;
+mod saves;
 mod scrapers;
+mod steam_client;
 mod steamapi;
+mod thumbnails;
+mod token_index;
 mod torrent_manager;
 
 #[tauri::command]
@@ -80,7 +92,7 @@ async fn check_defender_exclusion() -> Result<bool, String> {
 
     #[cfg(target_os = "windows")]
     {
-        let config = load_config();
+        let config = load_config()?;
         return Ok(config.defender_excluded);
     }
 }
@@ -88,10 +100,10 @@ async fn check_defender_exclusion() -> Result<bool, String> {
 // Add a helper function to update the Defender exclusion status
 #[tauri::command]
 async fn set_defender_exclusion_status(status: bool) -> Result<(), String> {
-    let mut config = load_config();
-    config.defender_excluded = status;
-    save_config(&config);
-    Ok(())
+    update_config(|config| {
+        config.defender_excluded = status;
+        Ok(())
+    })
 }
 
 #[tauri::command]
@@ -136,6 +148,27 @@ async fn drop_torrent(id: u64) -> Result<(), String> {
     }
 }
 
+#[tauri::command]
+async fn verify_game(
+    game_title: String,
+    repair: bool,
+) -> Result<torrent_manager::VerifyReport, String> {
+    // Get a clone of the torrent manager without holding the lock
+    let manager_clone = {
+        let manager = TORRENT_MANAGER.lock().await;
+        if let Some(m) = manager.as_ref() {
+            m.clone()
+        } else {
+            return Err("Torrent manager not initialized".into());
+        }
+    };
+
+    manager_clone
+        .verify_game(&game_title, repair)
+        .await
+        .map_err(|e| format!("Failed to verify {}: {}", game_title, e))
+}
+
 #[tauri::command]
 async fn download_torrent(game_title: String) -> Result<String, String> {
     let mut auth_client = AUTH_CLIENT.lock().await;
@@ -344,15 +377,15 @@ fn extract_magnet_link(html: &str) -> Result<String, String> {
 
 #[tauri::command]
 async fn find_and_get_game_details(query: String) -> Result<Option<GameDetails>, String> {
-    // Lock the global Steam game store and perform a fuzzy search
-    let game_store = STEAM_GAME_STORE.lock().await;
-    if let Some(game) = game_store.fuzzy_search(&query) {
+    // Resolve the title through the manual matches and the Steam catalog
+    catalog::wait_until_loaded().await;
+    if let Some(appid) = catalog::resolve_appid(&query) {
         // Fetch the game details using the AppID
-        match fetch_game_details(game.appid).await {
+        match details_cache::get_game_details(appid).await {
             Ok(details) => Ok(Some(details)),
             Err(err) => Err(format!(
                 "Failed to fetch game details for '{}': {}",
-                game.name, err
+                query, err
             )),
         }
     } else {
@@ -363,7 +396,7 @@ async fn find_and_get_game_details(query: String) -> Result<Option<GameDetails>,
 #[tauri::command]
 async fn find_and_get_game_details_library(query: String) -> Result<Option<GameDetails>, String> {
     // Load the config to check for cached game images
-    let config = load_config();
+    let config = load_config()?;
 
     if let Some(image_url) = config.game_images.get(&query) {
         // Return cached details with defaults for other fields
@@ -372,21 +405,23 @@ async fn find_and_get_game_details_library(query: String) -> Result<Option<GameD
         return Ok(Some(game_details));
     }
 
-    // Lock the global Steam game store and perform a fuzzy search
-    let game_store = STEAM_GAME_STORE.lock().await;
-    if let Some(game) = game_store.fuzzy_search(&query) {
+    // Resolve the title through the manual matches and the Steam catalog
+    catalog::wait_until_loaded().await;
+    if let Some(appid) = catalog::resolve_appid(&query) {
         // Fetch the game details using the AppID
-        match fetch_game_details(game.appid).await {
+        match details_cache::get_game_details(appid).await {
             Ok(details) => {
                 // Save the image URL to the config
                 if let Some(ref header_image) = details.header_image {
-                    save_game_image_to_config(&query, header_image);
+                    if let Err(e) = save_game_image_to_config(&query, header_image) {
+                        log::warn!("[CONFIG] Failed to remember the image of {}: {}", query, e);
+                    }
                 }
                 Ok(Some(details))
             }
             Err(err) => Err(format!(
                 "Failed to fetch game details for '{}': {}",
-                game.name, err
+                query, err
             )),
         }
     } else {
@@ -394,6 +429,143 @@ async fn find_and_get_game_details_library(query: String) -> Result<Option<GameD
     }
 }
 
+/// Screenshots and trailers of a game. Their images are prefetched in the
+/// background, replacing the prefetch of the previously viewed game.
+#[tauri::command]
+async fn get_game_media(appid: u32) -> Result<media::MediaGallery, String> {
+    let details = details_cache::get_game_details(appid).await?;
+    media::prefetch(appid, &details);
+    Ok(media::gallery(appid, &details))
+}
+
+/// Description of a game as sanitized HTML, markdown or plain text
+#[tauri::command]
+async fn get_game_description(appid: u32, format: rich_text::TextFormat) -> Result<String, String> {
+    let details = details_cache::get_game_details(appid).await?;
+    let html = details
+        .detailed_description
+        .or(details.about_the_game)
+        .unwrap_or_default();
+
+    Ok(match format {
+        rich_text::TextFormat::Html => html,
+        rich_text::TextFormat::Markdown => rich_text::to_markdown(&html, false),
+        rich_text::TextFormat::Text => rich_text::to_markdown(&html, true),
+    })
+}
+
+#[tauri::command]
+fn get_details_cache_stats() -> details_cache::DetailsCacheStats {
+    details_cache::cache_stats()
+}
+
+#[tauri::command]
+fn clear_details_cache(appid: Option<u32>) -> Result<(), String> {
+    details_cache::clear_cache(appid).map_err(|e| format!("Failed to clear cache: {}", e))
+}
+
+#[tauri::command]
+fn get_store_locale() -> Result<steamapi::Locale, String> {
+    load_config().map(|config| config.store_locale)
+}
+
+/// Set the language (e.g. `german`) and country code (e.g. `de`) of store
+/// details, `None` restores Steam's default
+#[tauri::command]
+fn set_store_locale(language: Option<String>, country_code: Option<String>) -> Result<(), String> {
+    let language = language
+        .map(|l| l.trim().to_lowercase())
+        .filter(|l| !l.is_empty());
+    let country_code = country_code
+        .map(|c| c.trim().to_lowercase())
+        .filter(|c| !c.is_empty());
+
+    if let Some(language) = &language {
+        if !language.chars().all(|c| c.is_ascii_lowercase() || c == '_') {
+            return Err(format!("Invalid language: {}", language));
+        }
+    }
+    if let Some(country_code) = &country_code {
+        if country_code.len() != 2 || !country_code.chars().all(|c| c.is_ascii_lowercase()) {
+            return Err(format!("Invalid country code: {}", country_code));
+        }
+    }
+
+    update_config(|config| {
+        config.store_locale = steamapi::Locale {
+            language,
+            country_code,
+        };
+        Ok(())
+    })
+}
+
+/// Port and session token the frontend needs to build proxy URLs, an error
+/// when the proxy could not be started
+#[tauri::command]
+fn get_proxy_info() -> Result<proxy::ProxyInfo, String> {
+    let info = proxy::info();
+    if info.port == 0 {
+        return Err("The image proxy is not running".to_string());
+    }
+    Ok(info)
+}
+
+/// Fix the image proxy port, `None` lets the system pick one. Takes effect on
+/// the next start.
+#[tauri::command]
+fn set_proxy_port(port: Option<u16>) -> Result<(), String> {
+    if let Some(port) = port {
+        if port < 1024 {
+            return Err(format!("Invalid port: {}", port));
+        }
+    }
+    update_config(|config| {
+        config.proxy_port = port;
+        Ok(())
+    })
+}
+
+/// Image hosts the proxy may fetch from besides Steam and the catalog hosts
+#[tauri::command]
+fn get_proxy_hosts() -> Result<Vec<String>, String> {
+    load_config().map(|config| config.proxy_hosts)
+}
+
+/// Replace the extra proxy hosts, each one also allows its subdomains
+#[tauri::command]
+fn set_proxy_hosts(hosts: Vec<String>) -> Result<(), String> {
+    let mut cleaned: Vec<String> = Vec::new();
+    for host in hosts {
+        let host = host
+            .trim()
+            .trim_start_matches("*.")
+            .trim_matches('.')
+            .to_lowercase();
+        if host.is_empty() {
+            continue;
+        }
+        let valid = host.contains('.')
+            && host.parse::<std::net::IpAddr>().is_err()
+            && host
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+        if !valid {
+            return Err(format!("Invalid host: {}", host));
+        }
+        if !cleaned.contains(&host) {
+            cleaned.push(host);
+        }
+    }
+
+    update_config(|config| {
+        config.proxy_hosts = cleaned.clone();
+        Ok(())
+    })?;
+    proxy_guard::set_extra_hosts(cleaned);
+    Ok(())
+}
+
 #[derive(serde::Serialize, Deserialize, Debug)]
 pub struct DownloadProgress {
     id: u64,
@@ -425,27 +597,28 @@ async fn get_downloads() -> Result<Vec<DownloadProgress>, String> {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
     // Create a Tokio runtime
     let runtime = Runtime::new().expect("Failed to create Tokio runtime");
-    // Load Steam games synchronously before proceeding
-    runtime.block_on(async {
-        match load_steam_games("steam_games.json").await {
-            Ok(game_store) => {
-                let mut global_store = STEAM_GAME_STORE.lock().await;
-                *global_store = game_store.lock().await.to_owned();
-                println!("Successfully loaded Steam games into global storage.");
+    // Load the Steam catalog in the background so the window opens right away,
+    // commands that need it wait for `catalog::wait_until_loaded`
+    runtime.spawn_blocking(|| {
+        let steam_games_path = catalog_refresh::steam_games_path();
+        match SteamCatalog::load(&steam_games_path, "games_index.json") {
+            Ok(loaded) => {
+                println!(
+                    "Successfully loaded {} Steam apps into the catalog.",
+                    loaded.len()
+                );
+                set_catalog(loaded);
             }
             Err(err) => {
-                eprintln!("Failed to load Steam games: {}", err);
+                eprintln!("Failed to load Steam catalog: {}", err);
+                set_catalog(SteamCatalog::default());
             }
         }
-
-        let mut global_store = STEAM_GAME_STORE_INDEX.lock().await;
-        global_store
-            .load_games("games_index.json")
-            .await
-            .expect("Failed to load steam games");
-
+    });
+    runtime.block_on(async {
         // Initialize torrent manager with platform-specific download directory
         let downloads_dir = if cfg!(target_os = "windows") {
             format!(
@@ -470,10 +643,11 @@ pub fn run() {
         *TORRENT_MANAGER.lock().await = Some(Arc::new(manager));
     });
     create_default_directories();
-    // Spawn the proxy server inside the runtime
-    runtime.spawn(async {
-        proxy::start_proxy().await;
-    });
+    // Bind the proxy before the window opens so image URLs know its port
+    match runtime.block_on(proxy::start_proxy()) {
+        Ok(port) => log::info!("[PROXY] Listening on 127.0.0.1:{}", port),
+        Err(e) => log::error!("[PROXY] {}", e),
+    }
 
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
@@ -498,35 +672,128 @@ pub fn run() {
             search_igggames,
             download_igggames,
             fetch_games_index,
+            verify_game,
+            backup_saves,
+            restore_saves,
+            list_save_backups,
+            get_save_locations,
+            set_save_locations,
+            launch_game,
+            check_system_requirements,
+            import_game,
+            get_library_usage,
+            query_library,
+            get_collections,
+            create_collection,
+            rename_collection,
+            delete_collection,
+            add_game_to_collection,
+            remove_game_from_collection,
+            update_game_library_data,
+            get_details_cache_stats,
+            get_game_description,
+            get_game_media,
+            clear_details_cache,
+            get_store_locale,
+            set_store_locale,
+            get_proxy_hosts,
+            set_proxy_hosts,
+            get_proxy_info,
+            set_proxy_port,
+            search_catalog,
+            get_catalog_facets,
+            get_related_apps,
+            is_catalog_loaded,
+            refresh_catalog,
+            set_steam_match,
+            clear_steam_match,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
 
 #[tauri::command]
-async fn fetch_games_index(category: String, page: usize, page_size: usize) -> Vec<SteamGame> {
-    let store = STEAM_GAME_STORE_INDEX.lock().await;
-    store.get_games(&category, page, page_size).await
+async fn fetch_games_index(
+    category: String,
+    page: usize,
+    page_size: usize,
+    filter: Option<BrowseFilter>,
+) -> Result<BrowsePage, String> {
+    loaded_catalog()
+        .await
+        .browse(&category, &filter.unwrap_or_default(), page, page_size)
 }
 
 #[tauri::command]
-fn update_recent_games(name: String, path: String) {
-    let mut config = load_config();
-    let new_game = RecentGameEntry { name, path };
+async fn get_catalog_facets(kind: FacetKind, limit: Option<usize>) -> Vec<FacetCount> {
+    let catalog = loaded_catalog().await;
+    let facets = catalog.facets(kind);
+    facets[..limit.unwrap_or(facets.len()).min(facets.len())].to_vec()
+}
 
-    // Remove existing entries with the same name or path
-    config
-        .recent_games
-        .retain(|g| g.name != new_game.name && g.path != new_game.path);
+#[tauri::command]
+async fn refresh_catalog(source: Option<String>) -> Result<catalog::CatalogDiff, String> {
+    catalog_refresh::refresh_catalog(source).await
+}
 
-    config.recent_games.insert(0, new_game);
-    config.recent_games.truncate(3);
-    save_config(&config);
+#[tauri::command]
+async fn get_related_apps(appid: u32) -> Result<catalog::RelatedApps, String> {
+    catalog::related_apps(appid).await
 }
 
 #[tauri::command]
-fn get_recent_games() -> Vec<RecentGameEntry> {
-    load_config().recent_games
+fn is_catalog_loaded() -> bool {
+    catalog::is_loaded()
+}
+
+#[tauri::command]
+async fn search_catalog(query: String, limit: usize) -> Vec<catalog::CatalogMatch> {
+    loaded_catalog().await.search(&query, limit.min(100))
+}
+
+#[tauri::command]
+fn set_steam_match(title: String, appid: u32) -> Result<(), String> {
+    let catalog = catalog();
+    if catalog.len() > 0 && catalog.get(appid).is_none() {
+        return Err(format!("Unknown Steam appid: {}", appid));
+    }
+
+    update_config(|config| {
+        config.steam_matches.insert(title.clone(), appid);
+        // The cached image belongs to the previous match
+        config.game_images.remove(&title);
+        Ok(())
+    })
+}
+
+#[tauri::command]
+fn clear_steam_match(title: String) -> Result<(), String> {
+    update_config(|config| {
+        if config.steam_matches.remove(&title).is_some() {
+            config.game_images.remove(&title);
+        }
+        Ok(())
+    })
+}
+
+#[tauri::command]
+fn update_recent_games(name: String, path: String) -> Result<(), String> {
+    let new_game = RecentGameEntry { name, path };
+    update_config(|config| {
+        // Remove existing entries with the same name or path
+        config
+            .recent_games
+            .retain(|g| g.name != new_game.name && g.path != new_game.path);
+
+        config.recent_games.insert(0, new_game);
+        config.recent_games.truncate(3);
+        Ok(())
+    })
+}
+
+#[tauri::command]
+fn get_recent_games() -> Result<Vec<RecentGameEntry>, String> {
+    load_config().map(|config| config.recent_games)
 }
 
 #[cfg(target_os = "windows")]
@@ -585,159 +852,314 @@ fn create_default_directories() {
     println!("Downloads directory: {}", downloads_dir);
 }
 
-#[derive(serde::Serialize)]
-struct InstalledGame {
-    name: String, // The name of the game folder
-    path: String, // The absolute path to the game
-}
-
-#[tauri::command]
-async fn get_installed_games() -> Result<Vec<InstalledGame>, String> {
-    use std::fs;
-    use std::path::PathBuf;
-
-    let games_dir = if cfg!(target_os = "windows") {
-        format!(
+// Folder the torrent manager downloads and extracts games into
+fn games_dir() -> PathBuf {
+    if cfg!(target_os = "windows") {
+        PathBuf::from(format!(
             "{}/Downloads/PirateLand",
-            std::env::var("USERPROFILE").unwrap_or_else(|_| {
-                println!("[DEBUG] USERPROFILE env var missing");
-                ".".to_string()
-            })
-        )
+            std::env::var("USERPROFILE").unwrap_or_else(|_| ".".to_string())
+        ))
     } else {
         let base_dir = std::env::var("XDG_DOWNLOAD_DIR").unwrap_or_else(|_| {
             format!(
                 "{}/Downloads",
-                std::env::var("HOME").unwrap_or_else(|_| {
-                    println!("[DEBUG] HOME env var missing");
-                    ".".to_string()
-                })
+                std::env::var("HOME").unwrap_or_else(|_| ".".to_string())
             )
         });
-        format!("{}/PirateLand", base_dir)
-    };
+        PathBuf::from(format!("{}/PirateLand", base_dir))
+    }
+}
 
-    println!("[DEBUG] Games directory: {}", games_dir);
-    let dir_path = PathBuf::from(&games_dir);
-    if !dir_path.exists() {
-        println!("[DEBUG] Games directory does not exist.");
-        return Ok(vec![]);
+#[derive(serde::Serialize)]
+struct InstalledGame {
+    name: String,       // The name of the game folder
+    path: String,       // The absolute path to the game
+    executable: String, // The detected main executable
+}
+
+#[tauri::command]
+async fn get_installed_games() -> Result<Vec<InstalledGame>, String> {
+    let config = load_config()?;
+    let default_root = games_dir();
+
+    // The download folder first, then any extra library roots
+    let mut roots = vec![default_root.clone()];
+    for root in &config.library_roots {
+        let root = PathBuf::from(root);
+        if !roots.contains(&root) {
+            roots.push(root);
+        }
     }
 
     let mut installed_games = Vec::new();
 
-    match fs::read_dir(&dir_path) {
-        Ok(entries) => {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    println!("[DEBUG] Found folder: {}", path.display());
-
-                    if path.is_dir() {
-                        if let Some(folder_name) = path.file_name().and_then(|n| n.to_str()) {
-                            let extracted_path = path.join("Extracted");
-                            let game_path = if extracted_path.exists() && extracted_path.is_dir() {
-                                println!("[DEBUG] Found Extracted folder inside {}", folder_name);
-                                extracted_path
-                            } else {
-                                println!(
-                                    "[DEBUG] No Extracted folder, using folder itself: {}",
-                                    folder_name
-                                );
-                                path.clone()
-                            };
-
-                            println!("[DEBUG] Checking for game files in {}", game_path.display());
-
-                            let has_game_files = fs::read_dir(&game_path)
-                                .ok()
-                                .map(|entries| {
-                                    entries.filter_map(|e| e.ok()).any(|e| {
-                                        let p = e.path();
-                                        if p.is_file() {
-                                            let ext = p
-                                                .extension()
-                                                .and_then(|s| s.to_str())
-                                                .unwrap_or("")
-                                                .to_lowercase();
-                                            let is_game_file = matches!(
-                                                ext.as_str(),
-                                                "exe" | "so" | "bin" | "appimage" | "sh"
-                                            );
-
-                                            println!(
-                                                "[DEBUG] Found file: {} (ext: {}) => game file? {}",
-                                                p.display(),
-                                                ext,
-                                                is_game_file
-                                            );
-
-                                            is_game_file
-                                        } else {
-                                            false
-                                        }
-                                    })
-                                })
-                                .unwrap_or(false);
-
-                            println!("[DEBUG] Has game files: {}", has_game_files);
-
-                            if has_game_files {
-                                installed_games.push(InstalledGame {
-                                    name: folder_name.to_string(),
-                                    path: game_path.to_string_lossy().to_string(),
-                                });
-                            }
-                        }
+    for games_dir in &roots {
+        log::debug!("[LIBRARY] Games directory: {}", games_dir.display());
+        if !games_dir.exists() {
+            log::debug!("[LIBRARY] Games directory does not exist.");
+            continue;
+        }
+
+        match fs::read_dir(games_dir) {
+            Ok(entries) => {
+                for entry in entries.flatten() {
+                    if let Some(game) = installed_game_from_dir(&entry.path()) {
+                        installed_games.push(game);
                     }
                 }
             }
+            Err(e) => {
+                let err_msg = format!("Failed to read games directory: {}", e);
+                log::debug!("[LIBRARY] {}", err_msg);
+                // Extra roots may live on drives that are not mounted right now
+                if games_dir == &default_root {
+                    return Err(err_msg);
+                }
+            }
         }
-        Err(e) => {
-            let err_msg = format!("Failed to read games directory: {}", e);
-            println!("[DEBUG] {}", err_msg);
-            return Err(err_msg);
+    }
+
+    // Games imported in place
+    for path in &config.imported_games {
+        if let Some(game) = installed_game_from_dir(Path::new(path)) {
+            installed_games.push(game);
         }
     }
 
-    println!("[DEBUG] Installed games found: {}", installed_games.len());
+    log::debug!("[LIBRARY] Installed games found: {}", installed_games.len());
     Ok(installed_games)
 }
 
+// Whole folder of a listed game, downloads list their `Extracted` subfolder
+fn game_folder(game_path: &Path) -> PathBuf {
+    match game_path.parent() {
+        Some(parent) if game_path.ends_with("Extracted") => parent.to_path_buf(),
+        _ => game_path.to_path_buf(),
+    }
+}
+
+// Turn a game folder into a library entry if it contains anything playable
+fn installed_game_from_dir(path: &Path) -> Option<InstalledGame> {
+    log::debug!("[LIBRARY] Found folder: {}", path.display());
+
+    if !path.is_dir() {
+        return None;
+    }
+    let folder_name = path.file_name().and_then(|n| n.to_str())?;
+
+    let extracted_path = path.join("Extracted");
+    let game_path = if extracted_path.exists() && extracted_path.is_dir() {
+        log::debug!("[LIBRARY] Found Extracted folder inside {}", folder_name);
+        extracted_path
+    } else {
+        log::debug!(
+            "[LIBRARY] No Extracted folder, using folder itself: {}",
+            folder_name
+        );
+        path.to_path_buf()
+    };
+
+    log::debug!(
+        "[LIBRARY] Checking for game files in {}",
+        game_path.display()
+    );
+
+    // Same detection as `import_game`, a recorded executable wins
+    let executable = manifest::load_manifest(path)
+        .and_then(|manifest| manifest.executable)
+        .map(|relative| path.join(relative))
+        .filter(|executable| executable.is_file())
+        .or_else(|| library::detect_executable(&game_path, folder_name));
+
+    log::debug!("[LIBRARY] Executable: {:?}", executable);
+
+    executable.map(|executable| InstalledGame {
+        name: folder_name.to_string(),
+        path: game_path.to_string_lossy().to_string(),
+        executable: executable.to_string_lossy().to_string(),
+    })
+}
+
+#[tauri::command]
+async fn import_game(
+    path: String,
+    library_root: Option<String>,
+) -> Result<library::ImportedGame, String> {
+    // Executable detection and moving the folder block on disk IO
+    tokio::task::spawn_blocking(move || {
+        library::import_game(Path::new(&path), library_root.as_deref().map(Path::new))
+    })
+    .await
+    .map_err(|e| format!("Blocking task failed: {}", e))?
+    .map_err(|e| format!("Failed to import game: {}", e))
+}
+
+#[tauri::command]
+async fn get_library_usage() -> Result<Vec<library::GameUsage>, String> {
+    // Sizes cover the whole game folder, not only the `Extracted` part
+    let games: Vec<(String, PathBuf)> = get_installed_games()
+        .await?
+        .into_iter()
+        .map(|game| {
+            let game_dir = game_folder(Path::new(&game.path));
+            (game.name, game_dir)
+        })
+        .collect();
+
+    tokio::task::spawn_blocking(move || library::library_usage(&games))
+        .await
+        .map_err(|e| format!("Blocking task failed: {}", e))
+}
+
+#[tauri::command]
+async fn query_library(query: library::LibraryQuery) -> Result<Vec<library::LibraryGame>, String> {
+    let games = get_installed_games()
+        .await?
+        .into_iter()
+        .map(|game| (game.name, game.path))
+        .collect();
+    library::query_library(games, &query).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_collections() -> Result<Vec<String>, String> {
+    load_config().map(|config| config.collections)
+}
+
+#[tauri::command]
+fn create_collection(name: String) -> Result<(), String> {
+    library::create_collection(&name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn rename_collection(old_name: String, new_name: String) -> Result<(), String> {
+    library::rename_collection(&old_name, &new_name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn delete_collection(name: String) -> Result<(), String> {
+    library::delete_collection(&name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn add_game_to_collection(game_name: String, collection: String) -> Result<(), String> {
+    library::set_game_in_collection(&game_name, &collection, true).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn remove_game_from_collection(game_name: String, collection: String) -> Result<(), String> {
+    library::set_game_in_collection(&game_name, &collection, false).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn update_game_library_data(
+    game_name: String,
+    favorite: Option<bool>,
+    tags: Option<Vec<String>>,
+    notes: Option<String>,
+) -> Result<(), String> {
+    library::update_game_data(&game_name, favorite, tags, notes).map_err(|e| e.to_string())
+}
+
+// Only folders the library lists can be uninstalled, wherever they live
 #[tauri::command]
 async fn uninstall_game(game_path: String) -> Result<(), String> {
-    // Define the parent directory
-    let games_dir = format!(
-        "{}/Downloads/PirateLand",
-        std::env::var("USERPROFILE").unwrap()
-    );
-    let games_dir_path = PathBuf::from(&games_dir);
+    let game = get_installed_games()
+        .await?
+        .into_iter()
+        .find(|game| game.path == game_path)
+        .ok_or("The specified path is not an installed game.")?;
+    let game_path = PathBuf::from(&game.path);
+    let game_folder = game_folder(&game_path);
+
+    // Keep a copy of the saves before the game folder disappears
+    saves::backup_saves(&game.name, Some(&game_path))
+        .map_err(|e| format!("Failed to back up saves before uninstall: {}", e))?;
+
+    fs::remove_dir_all(&game_folder)
+        .map_err(|e| format!("Failed to delete the directory: {}", e))?;
+
+    // An imported game would otherwise stay listed as a missing folder
+    let game_folder = game_folder.to_string_lossy().to_string();
+    update_config(|config| {
+        config.imported_games.retain(|p| *p != game_folder);
+        Ok(())
+    })
+}
 
-    // Ensure the game path is within the games directory for safety
-    let target_path = PathBuf::from(&game_path);
-    if !target_path.starts_with(&games_dir_path) {
-        return Err("The specified path is not within the allowed directory.".to_string());
-    }
+#[tauri::command]
+async fn backup_saves(
+    game_name: String,
+    game_path: String,
+) -> Result<Option<saves::SaveBackup>, String> {
+    saves::backup_saves(&game_name, Some(Path::new(&game_path)))
+        .map_err(|e| format!("Failed to back up saves for {}: {}", game_name, e))
+}
 
-    // Get the parent directory of the game folder (the folder containing the game)
-    let parent_dir = target_path.parent().ok_or_else(|| {
-        "Unable to determine the parent directory of the specified path.".to_string()
-    })?;
+#[tauri::command]
+async fn restore_saves(
+    game_name: String,
+    game_path: String,
+    file_name: String,
+) -> Result<(), String> {
+    saves::restore_saves(&game_name, &file_name, Some(Path::new(&game_path)))
+        .map_err(|e| format!("Failed to restore saves for {}: {}", game_name, e))
+}
 
-    // Check if the parent directory exists and is within the allowed games directory
-    if !parent_dir.starts_with(&games_dir_path) {
-        return Err("The parent directory is not within the allowed directory.".to_string());
-    }
+#[tauri::command]
+fn list_save_backups(game_name: String) -> Result<Vec<saves::SaveBackup>, String> {
+    saves::list_backups(&game_name).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_save_locations(game_name: String, game_path: String) -> Vec<String> {
+    saves::resolve_save_locations(&game_name, Some(Path::new(&game_path)))
+        .into_iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect()
+}
 
-    if parent_dir.exists() && parent_dir.is_dir() {
-        // Attempt to remove the parent directory recursively
-        match fs::remove_dir_all(parent_dir) {
-            Ok(_) => Ok(()), // Successfully deleted
-            Err(e) => Err(format!("Failed to delete the directory: {}", e)), // Error while deleting
+// Manual override of the save folders, an empty list restores the default rules
+#[tauri::command]
+fn set_save_locations(game_name: String, locations: Vec<String>) -> Result<(), String> {
+    update_config(|config| {
+        if locations.is_empty() {
+            config.save_locations.remove(&game_name);
+        } else {
+            config.save_locations.insert(game_name, locations);
         }
-    } else {
-        Err("The specified directory does not exist.".to_string()) // Path not found
+        Ok(())
+    })
+}
+
+// `game_path` is the folder the library lists the game under
+#[tauri::command]
+async fn launch_game(
+    game_name: String,
+    game_path: String,
+    executable_path: String,
+) -> Result<(), String> {
+    let executable = PathBuf::from(&executable_path);
+    let mut command = Command::new(&executable);
+    if let Some(parent) = executable.parent() {
+        command.current_dir(parent);
     }
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to launch {}: {}", executable_path, e))?;
+
+    // Back up the saves once the play session is over
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = child.wait() {
+            log::warn!("[SAVES] Failed to wait for {}: {}", game_name, e);
+            return;
+        }
+        if let Err(e) = saves::backup_saves(&game_name, Some(Path::new(&game_path))) {
+            log::warn!("[SAVES] Failed to back up saves for {}: {}", game_name, e);
+        }
+    });
+
+    Ok(())
 }
 
 #[derive(Debug, Serialize)]
@@ -747,6 +1169,19 @@ struct SearchResult {
     source: String,
 }
 
+#[tauri::command]
+async fn check_system_requirements(appid: u32) -> Result<requirements::RequirementsCheck, String> {
+    let details = details_cache::get_game_details(appid).await?;
+    let hardware = tokio::task::spawn_blocking(|| hardware::detect_hardware(&games_dir()))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(requirements::RequirementsCheck::new(
+        &details.pc_requirements.unwrap_or_default(),
+        hardware,
+    ))
+}
+
 #[tauri::command]
 async fn search_igggames(query: String) -> Result<Vec<SearchResult>, String> {
     let client = Client::new();
@@ -0,0 +1,591 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::catalog::{catalog, resolve_appid};
+use crate::config::{load_config, update_config};
+use crate::manifest::{load_manifest, save_manifest, GameManifest};
+
+// How deep executable detection looks inside a game folder
+const MAX_SCAN_DEPTH: usize = 3;
+
+// Executables shipped next to games that are never the game itself
+const IGNORED_EXECUTABLES: &[&str] = &[
+    "unins",
+    "setup",
+    "redist",
+    "vcredist",
+    "vc_redist",
+    "dxsetup",
+    "crashhandler",
+    "crashreport",
+    "launcherhelper",
+    "easyanticheat",
+];
+
+// Executable names that say nothing about which game they start
+const GENERIC_STEMS: &[&str] = &[
+    "game", "play", "start", "run", "launch", "launcher", "main", "app", "client", "win64", "x64",
+];
+
+// Shorter stems match too many names by accident
+const MIN_STEM_LEN: usize = 3;
+
+#[derive(Serialize, Debug)]
+pub struct ImportedGame {
+    pub name: String,
+    pub path: String,
+    pub executable: String,
+    pub appid: Option<u32>,
+    pub steam_name: Option<String>,
+}
+
+fn is_executable(path: &Path) -> bool {
+    let ext = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    matches!(ext.as_str(), "exe" | "appimage" | "x86_64" | "sh")
+}
+
+fn collect_executables(dir: &Path, depth: usize, found: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            if depth < MAX_SCAN_DEPTH {
+                collect_executables(&path, depth + 1, found);
+            }
+        } else if is_executable(&path) {
+            let file_name = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("")
+                .to_lowercase();
+            if !IGNORED_EXECUTABLES.iter().any(|i| file_name.contains(i)) {
+                found.push(path);
+            }
+        }
+    }
+}
+
+// Lowercase letters and digits only, spacing and punctuation never matter
+fn simplify(name: &str) -> String {
+    name.to_lowercase()
+        .replace(|c: char| !c.is_alphanumeric(), "")
+}
+
+// Whether an executable stem names the game, generic stems never do
+fn stem_matches(stem: &str, game_name: &str) -> bool {
+    let stem = simplify(stem);
+    stem.chars().count() >= MIN_STEM_LEN
+        && !GENERIC_STEMS.contains(&stem.as_str())
+        && simplify(game_name).contains(&stem)
+}
+
+/// Pick the most likely main executable of a game folder. Executables named
+/// like the game win, then the ones closest to the root, then the biggest.
+/// `game_name` is the name of the game folder, `game_dir` may be a subfolder
+/// of it such as `Extracted`.
+pub fn detect_executable(game_dir: &Path, game_name: &str) -> Option<PathBuf> {
+    let mut candidates = Vec::new();
+    collect_executables(game_dir, 0, &mut candidates);
+
+    candidates.into_iter().max_by_key(|path| {
+        let stem = path.file_stem().and_then(|n| n.to_str()).unwrap_or("");
+        let name_match = stem_matches(stem, game_name);
+        let depth = path
+            .strip_prefix(game_dir)
+            .map(|p| p.components().count())
+            .unwrap_or(usize::MAX);
+        let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        (name_match, std::cmp::Reverse(depth), size)
+    })
+}
+
+// `rename` fails across drives, fall back to copying and deleting
+fn move_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    copy_dir(from, to)?;
+    fs::remove_dir_all(from)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Add a game folder that was unpacked outside of the app to the library.
+///
+/// The folder is matched against the Steam catalog and gets a manifest. When
+/// `library_root` is given the folder is moved there first, otherwise it is
+/// remembered at its current location.
+pub fn import_game(path: &Path, library_root: Option<&Path>) -> anyhow::Result<ImportedGame> {
+    if !path.is_dir() {
+        anyhow::bail!("Folder does not exist: {}", path.display());
+    }
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .context("Folder has no name")?
+        .to_string();
+
+    let executable = detect_executable(path, &name)
+        .with_context(|| format!("No game executable found in {}", path.display()))?;
+    let relative_executable = executable
+        .strip_prefix(path)
+        .unwrap_or(&executable)
+        .to_path_buf();
+
+    let appid = resolve_appid(&name);
+    let steam_name = appid.and_then(|appid| catalog().get(appid).map(|g| g.name.clone()));
+
+    let game_dir = match library_root {
+        Some(root) => {
+            let target = root.join(&name);
+            if target.exists() {
+                anyhow::bail!("{} already exists", target.display());
+            }
+            fs::create_dir_all(root)?;
+            move_dir(path, &target)
+                .with_context(|| format!("Failed to move game to {}", target.display()))?;
+            target
+        }
+        None => path.to_path_buf(),
+    };
+
+    let mut manifest = load_manifest(&game_dir).unwrap_or_else(|| GameManifest {
+        title: name.clone(),
+        ..Default::default()
+    });
+    manifest.appid = appid.or(manifest.appid);
+    manifest.executable = Some(relative_executable.to_string_lossy().to_string());
+    save_manifest(&game_dir, &manifest)?;
+
+    // Remember where the game lives so the library lists it
+    let game_dir_str = game_dir.to_string_lossy().to_string();
+    update_config(|config| {
+        match library_root {
+            Some(root) => {
+                let root_str = root.to_string_lossy().to_string();
+                if !config.library_roots.contains(&root_str) {
+                    config.library_roots.push(root_str);
+                }
+                config.imported_games.retain(|p| Path::new(p) != path);
+            }
+            None => {
+                if !config.imported_games.contains(&game_dir_str) {
+                    config.imported_games.push(game_dir_str.clone());
+                }
+            }
+        }
+        Ok(())
+    })
+    .map_err(anyhow::Error::msg)?;
+
+    Ok(ImportedGame {
+        name,
+        path: game_dir_str,
+        executable: game_dir
+            .join(relative_executable)
+            .to_string_lossy()
+            .to_string(),
+        appid,
+        steam_name,
+    })
+}
+
+// Archive extensions, including the split parts of multi-volume RARs
+fn is_archive(path: &Path) -> bool {
+    let ext = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let is_volume =
+        ext.len() == 3 && ext.starts_with('r') && ext[1..].chars().all(|c| c.is_ascii_digit());
+    matches!(ext.as_str(), "rar" | "zip" | "7z") || is_volume
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GameUsage {
+    pub name: String,
+    pub path: String,
+    pub extracted_bytes: u64,
+    pub archive_bytes: u64,
+    pub payload_bytes: u64,
+    pub total_bytes: u64,
+    /// Archives can be deleted once they have been extracted
+    pub reclaimable_bytes: u64,
+    // Modification times the sizes were computed at
+    folder_mtime: u64,
+    extracted_mtime: u64,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct UsageCache {
+    games: HashMap<String, GameUsage>,
+}
+
+fn usage_cache_path() -> PathBuf {
+    let base_dir = dirs::cache_dir().unwrap_or_else(|| PathBuf::from("."));
+    base_dir.join("PirateLand").join("library_usage.json")
+}
+
+fn load_usage_cache() -> UsageCache {
+    fs::read_to_string(usage_cache_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_usage_cache(cache: &UsageCache) -> std::io::Result<()> {
+    let path = usage_cache_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string(cache)?)
+}
+
+fn mtime_secs(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .map(|entry| match entry.file_type() {
+            Ok(t) if t.is_dir() => dir_size(&entry.path()),
+            Ok(_) => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+fn compute_usage(name: &str, game_dir: &Path) -> GameUsage {
+    let extracted_dir = game_dir.join("Extracted");
+    let mut usage = GameUsage {
+        name: name.to_string(),
+        path: game_dir.to_string_lossy().to_string(),
+        extracted_bytes: dir_size(&extracted_dir),
+        folder_mtime: mtime_secs(game_dir),
+        extracted_mtime: mtime_secs(&extracted_dir),
+        ..Default::default()
+    };
+
+    // Everything next to `Extracted` is what the torrent downloaded
+    if let Ok(entries) = fs::read_dir(game_dir) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path == extracted_dir {
+                continue;
+            }
+            let size = if path.is_dir() {
+                dir_size(&path)
+            } else {
+                entry.metadata().map(|m| m.len()).unwrap_or(0)
+            };
+            if is_archive(&path) {
+                usage.archive_bytes += size;
+            } else {
+                usage.payload_bytes += size;
+            }
+        }
+    }
+
+    usage.total_bytes = usage.extracted_bytes + usage.archive_bytes + usage.payload_bytes;
+    if usage.extracted_bytes > 0 {
+        usage.reclaimable_bytes = usage.archive_bytes;
+    }
+    usage
+}
+
+/// Disk usage of the given game folders, biggest first. Sizes are cached and
+/// only recomputed for folders whose modification time changed.
+pub fn library_usage(games: &[(String, PathBuf)]) -> Vec<GameUsage> {
+    let mut cache = load_usage_cache();
+    let mut result = Vec::new();
+
+    for (name, game_dir) in games {
+        let key = game_dir.to_string_lossy().to_string();
+        let fresh = cache.games.get(&key).filter(|cached| {
+            cached.folder_mtime == mtime_secs(game_dir)
+                && cached.extracted_mtime == mtime_secs(&game_dir.join("Extracted"))
+        });
+
+        let usage = match fresh {
+            Some(cached) => cached.clone(),
+            None => {
+                let usage = compute_usage(name, game_dir);
+                cache.games.insert(key, usage.clone());
+                usage
+            }
+        };
+        result.push(usage);
+    }
+
+    // Drop folders that are no longer part of the library
+    let known: Vec<String> = games
+        .iter()
+        .map(|(_, dir)| dir.to_string_lossy().to_string())
+        .collect();
+    cache.games.retain(|key, _| known.contains(key));
+
+    if let Err(e) = save_usage_cache(&cache) {
+        log::warn!("[LIBRARY] Failed to save usage cache: {}", e);
+    }
+
+    result.sort_by(|a, b| b.total_bytes.cmp(&a.total_bytes));
+    result
+}
+
+pub fn create_collection(name: &str) -> anyhow::Result<()> {
+    let name = name.trim();
+    if name.is_empty() {
+        anyhow::bail!("Collection name cannot be empty");
+    }
+    update_config(|config| {
+        if config.collections.iter().any(|c| c == name) {
+            return Err(format!("Collection '{}' already exists", name));
+        }
+        config.collections.push(name.to_string());
+        Ok(())
+    })
+    .map_err(anyhow::Error::msg)
+}
+
+pub fn rename_collection(old_name: &str, new_name: &str) -> anyhow::Result<()> {
+    let new_name = new_name.trim();
+    if new_name.is_empty() {
+        anyhow::bail!("Collection name cannot be empty");
+    }
+    update_config(|config| {
+        if config.collections.iter().any(|c| c == new_name) {
+            return Err(format!("Collection '{}' already exists", new_name));
+        }
+        let collection = config
+            .collections
+            .iter_mut()
+            .find(|c| c.as_str() == old_name)
+            .ok_or_else(|| format!("Collection '{}' does not exist", old_name))?;
+        *collection = new_name.to_string();
+
+        for data in config.library_data.values_mut() {
+            for collection in data.collections.iter_mut() {
+                if collection == old_name {
+                    *collection = new_name.to_string();
+                }
+            }
+        }
+        Ok(())
+    })
+    .map_err(anyhow::Error::msg)
+}
+
+// Deleting a collection never touches the games in it
+pub fn delete_collection(name: &str) -> anyhow::Result<()> {
+    update_config(|config| {
+        let before = config.collections.len();
+        config.collections.retain(|c| c != name);
+        if config.collections.len() == before {
+            return Err(format!("Collection '{}' does not exist", name));
+        }
+        for data in config.library_data.values_mut() {
+            data.collections.retain(|c| c != name);
+        }
+        Ok(())
+    })
+    .map_err(anyhow::Error::msg)
+}
+
+pub fn set_game_in_collection(
+    game_name: &str,
+    collection: &str,
+    member: bool,
+) -> anyhow::Result<()> {
+    update_config(|config| {
+        if !config.collections.iter().any(|c| c == collection) {
+            return Err(format!("Collection '{}' does not exist", collection));
+        }
+        let data = config
+            .library_data
+            .entry(game_name.to_string())
+            .or_default();
+        data.collections.retain(|c| c != collection);
+        if member {
+            data.collections.push(collection.to_string());
+        }
+        Ok(())
+    })
+    .map_err(anyhow::Error::msg)
+}
+
+/// Update the favorite flag, tags and notes of a game. `None` leaves a field as it is.
+pub fn update_game_data(
+    game_name: &str,
+    favorite: Option<bool>,
+    tags: Option<Vec<String>>,
+    notes: Option<String>,
+) -> anyhow::Result<()> {
+    let tags = tags.map(|tags| {
+        let mut tags: Vec<String> = tags
+            .into_iter()
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    });
+    update_config(|config| {
+        let data = config
+            .library_data
+            .entry(game_name.to_string())
+            .or_default();
+        if let Some(favorite) = favorite {
+            data.favorite = favorite;
+        }
+        if let Some(tags) = tags {
+            data.tags = tags;
+        }
+        if let Some(notes) = notes {
+            data.notes = notes;
+        }
+        Ok(())
+    })
+    .map_err(anyhow::Error::msg)
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct LibraryQuery {
+    pub collection: Option<String>,
+    pub tag: Option<String>,
+    pub favorites_only: bool,
+    pub search: Option<String>,
+    /// "name" (default), "favorite" or "tags"
+    pub sort_by: Option<String>,
+    pub descending: bool,
+}
+
+#[derive(Serialize, Clone)]
+pub struct LibraryGame {
+    pub name: String,
+    pub path: String,
+    pub favorite: bool,
+    pub tags: Vec<String>,
+    pub notes: String,
+    pub collections: Vec<String>,
+}
+
+/// Attach the user data to the installed games and apply the query filters
+pub fn query_library(
+    games: Vec<(String, String)>,
+    query: &LibraryQuery,
+) -> anyhow::Result<Vec<LibraryGame>> {
+    let config = load_config().map_err(anyhow::Error::msg)?;
+    let search = query.search.as_ref().map(|s| s.to_lowercase());
+
+    let mut result: Vec<LibraryGame> = games
+        .into_iter()
+        .map(|(name, path)| {
+            let data = config.library_data.get(&name).cloned().unwrap_or_default();
+            LibraryGame {
+                name,
+                path,
+                favorite: data.favorite,
+                tags: data.tags,
+                notes: data.notes,
+                collections: data.collections,
+            }
+        })
+        .filter(|game| !query.favorites_only || game.favorite)
+        .filter(|game| match &query.collection {
+            Some(collection) => game.collections.contains(collection),
+            None => true,
+        })
+        .filter(|game| match &query.tag {
+            Some(tag) => game.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)),
+            None => true,
+        })
+        .filter(|game| match &search {
+            Some(search) => {
+                game.name.to_lowercase().contains(search)
+                    || game.notes.to_lowercase().contains(search)
+                    || game.tags.iter().any(|t| t.to_lowercase().contains(search))
+            }
+            None => true,
+        })
+        .collect();
+
+    let by_name =
+        |a: &LibraryGame, b: &LibraryGame| a.name.to_lowercase().cmp(&b.name.to_lowercase());
+    match query.sort_by.as_deref() {
+        Some("favorite") => {
+            result.sort_by(|a, b| b.favorite.cmp(&a.favorite).then_with(|| by_name(a, b)))
+        }
+        Some("tags") => {
+            result.sort_by(|a, b| b.tags.len().cmp(&a.tags.len()).then_with(|| by_name(a, b)))
+        }
+        _ => result.sort_by(by_name),
+    }
+    if query.descending {
+        result.reverse();
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_telling_stems_match_the_game() {
+        assert!(stem_matches("Hades", "Hades II"));
+        assert!(stem_matches("witcher3", "The Witcher 3 - Wild Hunt"));
+        assert!(!stem_matches("game", "Game of Thrones"));
+        assert!(!stem_matches("Launcher", "Anno Launcher Edition"));
+        assert!(!stem_matches("a", "Hades"));
+    }
+
+    #[test]
+    fn names_executables_after_the_game_folder() {
+        let game_dir = std::env::temp_dir()
+            .join(format!("pirateland-library-{}", std::process::id()))
+            .join("Hollow Knight");
+        let extracted = game_dir.join("Extracted");
+        fs::create_dir_all(extracted.join("bin")).unwrap();
+        fs::write(extracted.join("Launcher.exe"), vec![0; 64]).unwrap();
+        fs::write(extracted.join("bin").join("hollow_knight.exe"), [0; 8]).unwrap();
+
+        let executable = detect_executable(&extracted, "Hollow Knight").unwrap();
+
+        assert_eq!(executable, extracted.join("bin").join("hollow_knight.exe"));
+        fs::remove_dir_all(game_dir.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Name of the manifest file stored at the root of every game folder
+const MANIFEST_FILE: &str = "pirateland.json";
+
+/// Per-game metadata kept next to the game files so it survives config resets
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct GameManifest {
+    pub title: String,
+    /// Path to the `.torrent` file or the magnet link the game was downloaded from
+    pub torrent_source: Option<String>,
+    pub appid: Option<u32>,
+    /// Main executable, relative to the game folder
+    pub executable: Option<String>,
+}
+
+fn manifest_path(game_dir: &Path) -> PathBuf {
+    game_dir.join(MANIFEST_FILE)
+}
+
+// Load the manifest of a game folder, if there is one
+pub fn load_manifest(game_dir: &Path) -> Option<GameManifest> {
+    let content = fs::read_to_string(manifest_path(game_dir)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+// Save the manifest into the game folder
+pub fn save_manifest(game_dir: &Path, manifest: &GameManifest) -> std::io::Result<()> {
+    fs::create_dir_all(game_dir)?;
+    let content = serde_json::to_string_pretty(manifest)?;
+    fs::write(manifest_path(game_dir), content)
+}
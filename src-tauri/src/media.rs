@@ -0,0 +1,151 @@
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::image_cache;
+use crate::proxy::proxied_url;
+use crate::steamapi::GameDetails;
+
+// Images downloaded at once while prefetching
+const CONCURRENT_DOWNLOADS: usize = 4;
+
+// Game whose media is being prefetched, older prefetches stop early
+static CURRENT_PREFETCH: Mutex<Prefetch> = Mutex::new(Prefetch {
+    appid: None,
+    run: 0,
+});
+
+struct Prefetch {
+    // Unset once the latest run has finished
+    appid: Option<u32>,
+    run: u64,
+}
+
+fn is_current(run: u64) -> bool {
+    CURRENT_PREFETCH.lock().unwrap().run == run
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct GalleryImage {
+    pub id: Option<u32>,
+    pub thumbnail: Option<String>,
+    pub full: Option<String>,
+}
+
+/// A trailer with its stream URLs, unset formats are not offered by Steam.
+/// The progressive files stream through the proxy, HLS and DASH manifests
+/// refer to relative segments and stay direct.
+#[derive(Serialize, Debug, Clone)]
+pub struct GalleryMovie {
+    pub id: Option<u32>,
+    pub name: Option<String>,
+    pub thumbnail: Option<String>,
+    pub webm_480: Option<String>,
+    pub webm_max: Option<String>,
+    pub mp4_480: Option<String>,
+    pub mp4_max: Option<String>,
+    pub hls: Option<String>,
+    pub dash: Option<String>,
+    pub highlight: bool,
+}
+
+/// Screenshots and trailers of a game, loaded through the local proxy
+#[derive(Serialize, Debug, Clone)]
+pub struct MediaGallery {
+    pub appid: u32,
+    pub screenshots: Vec<GalleryImage>,
+    pub movies: Vec<GalleryMovie>,
+}
+
+pub fn gallery(appid: u32, details: &GameDetails) -> MediaGallery {
+    let proxied = |url: &Option<String>| url.as_deref().map(proxied_url);
+
+    let screenshots = details
+        .screenshots
+        .iter()
+        .flatten()
+        .map(|screenshot| GalleryImage {
+            id: screenshot.id,
+            thumbnail: proxied(&screenshot.path_thumbnail),
+            full: proxied(&screenshot.path_full),
+        })
+        .collect();
+
+    let movies = details
+        .movies
+        .iter()
+        .flatten()
+        .map(|movie| GalleryMovie {
+            id: movie.id,
+            name: movie.name.clone(),
+            thumbnail: proxied(&movie.thumbnail),
+            webm_480: movie.webm.as_ref().and_then(|f| proxied(&f.low)),
+            webm_max: movie.webm.as_ref().and_then(|f| proxied(&f.max)),
+            mp4_480: movie.mp4.as_ref().and_then(|f| proxied(&f.low)),
+            mp4_max: movie.mp4.as_ref().and_then(|f| proxied(&f.max)),
+            hls: movie.hls_h264.clone(),
+            dash: movie.dash_h264.clone(),
+            highlight: movie.highlight.unwrap_or(false),
+        })
+        .collect();
+
+    MediaGallery {
+        appid,
+        screenshots,
+        movies,
+    }
+}
+
+/// Load the thumbnails and then the full-size screenshots of a game into the
+/// image cache in the background. Starting a prefetch for another game stops this one,
+/// the same game is not prefetched twice at once.
+pub fn prefetch(appid: u32, details: &GameDetails) {
+    let run = {
+        let mut current = CURRENT_PREFETCH.lock().unwrap();
+        if current.appid == Some(appid) {
+            log::debug!("[MEDIA] Prefetch of {} already running", appid);
+            return;
+        }
+        current.appid = Some(appid);
+        current.run += 1;
+        current.run
+    };
+
+    let screenshots = details.screenshots.iter().flatten();
+    let movie_thumbnails = details.movies.iter().flatten().map(|m| &m.thumbnail);
+    // Thumbnails first so the gallery strip fills in quickly
+    let urls: Vec<String> = screenshots
+        .clone()
+        .map(|s| &s.path_thumbnail)
+        .chain(movie_thumbnails)
+        .chain(screenshots.map(|s| &s.path_full))
+        .flatten()
+        .cloned()
+        .collect();
+
+    tokio::spawn(async move {
+        let permits = Arc::new(Semaphore::new(CONCURRENT_DOWNLOADS));
+        let mut downloads = JoinSet::new();
+
+        for url in urls {
+            let permit = permits.clone().acquire_owned().await.unwrap();
+            if !is_current(run) {
+                log::debug!("[MEDIA] Prefetch of {} superseded", appid);
+                break;
+            }
+            downloads.spawn(async move {
+                let _permit = permit;
+                if let Err(e) = image_cache::store(&url).await {
+                    log::debug!("[MEDIA] Failed to prefetch {}: {}", url, e);
+                }
+            });
+        }
+        while downloads.join_next().await.is_some() {}
+
+        let mut current = CURRENT_PREFETCH.lock().unwrap();
+        if current.run == run {
+            current.appid = None;
+        }
+    });
+}
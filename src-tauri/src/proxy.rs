@@ -1,100 +1,287 @@
-use warp::Filter;
-use reqwest::Client;
+use once_cell::sync::{Lazy, OnceCell};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::convert::Infallible;
 use warp::http::StatusCode;
 use warp::hyper::Body;
-use std::convert::Infallible;
+use warp::Filter;
 
-pub async fn start_proxy() {
-    let client = Client::new();
+use crate::config::load_config_or_default;
+use crate::image_cache::{self, Fetched, OriginStream};
+use crate::proxy_guard;
+use crate::thumbnails::ResizeOptions;
 
-    let image_proxy = warp::path("proxy")
-        .and(warp::query::<std::collections::HashMap<String, String>>())
-        .and_then(move |params: std::collections::HashMap<String, String>| {
-            let client = client.clone();
-            async move {
-                if let Some(image_url) = params.get("url") {
-                    //println!("Received request to proxy image: {}", image_url);
-
-                    // Fetch the image from the URL
-                    let res = client
-                        .get(image_url)
-                        .header(
-                            "User-Agent",
-                            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36",
-                        )
-                        .header("Referer", "https://online-fix.me")
-                        .send()
-                    .await;
-
-                    match res {
-                        Ok(response) => {
-                            //println!("Successfully fetched image from: {}", image_url);
-
-                            // Clone the headers before consuming the response
-                            let headers_map = response.headers().clone();
-                            //println!("Response headers: {:?}", headers_map);
-
-                            // Consume the response to get the body
-                            let body = match response.bytes().await {
-                                Ok(bytes) => {
-                                    //println!("Successfully read response body");
-                                    bytes
-                                }
-                                Err(err) => {
-                                    //println!("Failed to read response body: {}", err);
-                                    return Ok::<_, Infallible>(warp::http::Response::builder()
-                                        .status(StatusCode::INTERNAL_SERVER_ERROR)
-                                        .body(Body::from("Failed to read image data"))
-                                        .unwrap());
-                                }
-                            };
-
-                            // Create a Warp HeaderMap and populate it
-                            let mut headers = warp::http::HeaderMap::new();
-
-                            if let Some(content_type) = headers_map.get(reqwest::header::CONTENT_TYPE) {
-                                headers.insert(
-                                    warp::http::header::CONTENT_TYPE,
-                                    content_type.to_str().unwrap_or("application/octet-stream").parse().unwrap(),
-                                );
-                            }
-                            // Add CORS headers
-                            headers.insert("Access-Control-Allow-Origin", "*".parse().unwrap());
-                            headers.insert("Access-Control-Allow-Methods", "GET, OPTIONS".parse().unwrap());
-                            headers.insert("Access-Control-Allow-Headers", "*".parse().unwrap());
-
-                            // Construct the response
-                            let mut builder = warp::http::Response::builder()
-                                .status(StatusCode::OK);
-
-                            for (key, value) in headers.iter() {
-                                builder = builder.header(key, value);
-                            }
-
-                            let response = builder.body(Body::from(body)).unwrap();
-                            Ok::<_, Infallible>(warp::reply::Response::from(response))
-                        }
-                        Err(err) => {
-                            //println!("Failed to fetch image from: {}. Error: {}", image_url, err);
-
-                            let error_response = warp::http::Response::builder()
-                                .status(StatusCode::BAD_GATEWAY)
-                                .body(Body::from("Failed to fetch image"))
-                                .unwrap();
-                            Ok::<_, Infallible>(warp::reply::Response::from(error_response))
-                        }
+// Port the proxy listens on, on localhost only, set once it is bound
+static PROXY_PORT: OnceCell<u16> = OnceCell::new();
+
+// Secret every proxy URL carries so other local processes cannot use the
+// proxy, 32 bytes from the OS random source
+static PROXY_TOKEN: Lazy<String> = Lazy::new(|| {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).expect("OS random source unavailable");
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+});
+
+// Compare in constant time so response timing does not leak the token
+fn token_matches(given: &str) -> bool {
+    let expected = PROXY_TOKEN.as_bytes();
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Where the frontend reaches the proxy this session
+#[derive(Serialize, Debug, Clone)]
+pub struct ProxyInfo {
+    pub port: u16,
+    pub token: String,
+}
+
+pub fn info() -> ProxyInfo {
+    ProxyInfo {
+        port: PROXY_PORT.get().copied().unwrap_or(0),
+        token: PROXY_TOKEN.clone(),
+    }
+}
+
+/// URL that loads `url` through the local proxy, `url` itself when the proxy
+/// is not running
+pub fn proxied_url(url: &str) -> String {
+    let Some(port) = PROXY_PORT.get() else {
+        return url.to_string();
+    };
+    let base = format!("http://127.0.0.1:{}/proxy", port);
+    reqwest::Url::parse_with_params(&base, &[("url", url), ("token", PROXY_TOKEN.as_str())])
+        .map(String::from)
+        .unwrap_or_else(|_| url.to_string())
+}
+
+// Response headers shared by every reply, CORS only for the app itself
+fn response_builder(origin: &Option<String>) -> warp::http::response::Builder {
+    let builder = warp::http::Response::builder().header(warp::http::header::VARY, "Origin");
+    match origin {
+        Some(origin) if proxy_guard::is_app_origin(origin) => builder
+            .header("Access-Control-Allow-Origin", origin.as_str())
+            .header("Access-Control-Allow-Methods", "GET, OPTIONS")
+            .header("Access-Control-Allow-Headers", "*"),
+        _ => builder,
+    }
+}
+
+fn error_response(
+    origin: &Option<String>,
+    status: StatusCode,
+    message: &'static str,
+) -> warp::reply::Response {
+    response_builder(origin)
+        .status(status)
+        .body(Body::from(message))
+        .unwrap()
+}
+
+// Origin headers passed on with streamed responses
+const PASSTHROUGH_HEADERS: &[&str] = &[
+    "content-type",
+    "content-length",
+    "content-range",
+    "accept-ranges",
+    "etag",
+    "last-modified",
+    "cache-control",
+    "expires",
+];
+
+// Forward an origin response chunk by chunk. The channel only takes the next
+// chunk once the webview has read the previous one, so the origin is never
+// read faster than the body is consumed.
+fn stream_response(origin: &Option<String>, stream: OriginStream) -> warp::reply::Response {
+    let OriginStream { head, mut response } = stream;
+    let status = response.status();
+    let passed = status.is_success()
+        || status == reqwest::StatusCode::NOT_MODIFIED
+        || status == reqwest::StatusCode::RANGE_NOT_SATISFIABLE;
+    if !passed {
+        log::debug!("[PROXY] {} returned {}", response.url(), status);
+        return error_response(origin, StatusCode::BAD_GATEWAY, "Failed to fetch media");
+    }
+
+    let mut builder = response_builder(origin).status(status.as_u16());
+    for &name in PASSTHROUGH_HEADERS {
+        if let Some(value) = response.headers().get(name) {
+            builder = builder.header(name, value.as_bytes());
+        }
+    }
+
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        if !head.is_empty() && sender.send_data(head).await.is_err() {
+            return;
+        }
+        loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    // The webview stopped reading
+                    if sender.send_data(chunk).await.is_err() {
+                        break;
                     }
-                } else {
-                    //println!("Missing 'url' parameter in request");
-                    let error_response = warp::http::Response::builder()
-                        .status(StatusCode::BAD_REQUEST)
-                        .body(Body::from("Missing 'url' parameter"))
-                        .unwrap();
-                    Ok::<_, Infallible>(warp::reply::Response::from(error_response))
                 }
+                Ok(None) => break,
+                Err(e) => {
+                    log::debug!("[PROXY] Stream from {} failed: {}", response.url(), e);
+                    sender.abort();
+                    break;
+                }
+            }
+        }
+    });
+
+    builder.body(body).unwrap()
+}
+
+// `If-None-Match` may list several tags or be a wildcard
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == "*" || tag == etag)
+}
+
+// Serve one proxy request, every failure becomes an error response
+async fn handle_proxy(
+    params: HashMap<String, String>,
+    if_none_match: Option<String>,
+    origin: Option<String>,
+    range: Option<String>,
+    if_range: Option<String>,
+) -> Result<warp::reply::Response, Infallible> {
+    if !params
+        .get("token")
+        .is_some_and(|token| token_matches(token))
+    {
+        return Ok(error_response(
+            &origin,
+            StatusCode::FORBIDDEN,
+            "Invalid token",
+        ));
+    }
+    let Some(image_url) = params.get("url") else {
+        return Ok(error_response(
+            &origin,
+            StatusCode::BAD_REQUEST,
+            "Missing 'url' parameter",
+        ));
+    };
+
+    let allowed = reqwest::Url::parse(image_url)
+        .map_err(|e| e.to_string())
+        .and_then(|url| proxy_guard::check_url(&url));
+    if let Err(e) = allowed {
+        log::warn!("[PROXY] Refused {}: {}", image_url, e);
+        return Ok(error_response(
+            &origin,
+            StatusCode::FORBIDDEN,
+            "URL not allowed",
+        ));
+    }
+
+    // Seeking in a video or resuming a download, always from the origin
+    if let Some(range) = range {
+        let fetched = image_cache::fetch_range(image_url, &range, if_range.as_deref()).await;
+        return Ok(match fetched {
+            Ok(response) => stream_response(&origin, response.into()),
+            Err(e) => {
+                log::debug!("[PROXY] {}: {}", image_url, e);
+                error_response(&origin, StatusCode::BAD_GATEWAY, "Failed to fetch media")
             }
         });
+    }
+
+    let resize = match ResizeOptions::from_query(&params) {
+        Ok(resize) => resize,
+        Err(e) => {
+            log::debug!("[PROXY] {}", e);
+            return Ok(error_response(
+                &origin,
+                StatusCode::BAD_REQUEST,
+                "Invalid resize parameters",
+            ));
+        }
+    };
+    let fetched = match &resize {
+        Some(resize) => image_cache::fetch_resized(image_url, resize).await,
+        None => image_cache::fetch(image_url).await,
+    };
+
+    let image = match fetched {
+        Ok(Fetched::Cached(image)) => image,
+        Ok(Fetched::Stream(stream)) => return Ok(stream_response(&origin, stream)),
+        Err(e) => {
+            log::debug!("[PROXY] {}: {}", image_url, e);
+            return Ok(error_response(
+                &origin,
+                StatusCode::BAD_GATEWAY,
+                "Failed to fetch image",
+            ));
+        }
+    };
+
+    let builder = response_builder(&origin)
+        .header(warp::http::header::ETAG, &image.etag)
+        .header(
+            warp::http::header::CACHE_CONTROL,
+            format!("max-age={}", image.max_age),
+        );
+
+    // The webview already has this image
+    if if_none_match.is_some_and(|tags| etag_matches(&tags, &image.etag)) {
+        return Ok(builder
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .unwrap());
+    }
+
+    Ok(builder
+        .status(StatusCode::OK)
+        .header(warp::http::header::CONTENT_TYPE, &image.content_type)
+        .header(warp::http::header::CONTENT_LENGTH, image.body.len())
+        .body(Body::from(image.body))
+        .unwrap())
+}
+
+/// Bind the proxy and serve it in the background, returning the port. The
+/// configured port is preferred, when it is unset or taken the system picks
+/// a free one.
+pub async fn start_proxy() -> Result<u16, String> {
+    let image_proxy = warp::path("proxy")
+        .and(warp::query::<HashMap<String, String>>())
+        .and(warp::header::optional::<String>("if-none-match"))
+        .and(warp::header::optional::<String>("origin"))
+        .and(warp::header::optional::<String>("range"))
+        .and(warp::header::optional::<String>("if-range"))
+        .and_then(handle_proxy);
+
+    let configured = load_config_or_default().proxy_port.unwrap_or(0);
+    let bound =
+        match warp::serve(image_proxy.clone()).try_bind_ephemeral(([127, 0, 0, 1], configured)) {
+            Ok(bound) => bound,
+            Err(e) if configured != 0 => {
+                log::warn!(
+                    "[PROXY] Port {} is unavailable ({}), using a free port",
+                    configured,
+                    e
+                );
+                warp::serve(image_proxy)
+                    .try_bind_ephemeral(([127, 0, 0, 1], 0))
+                    .map_err(|e| format!("Failed to start the image proxy: {}", e))?
+            }
+            Err(e) => return Err(format!("Failed to start the image proxy: {}", e)),
+        };
+    let (address, server) = bound;
 
-    //println!("Starting proxy server on http://127.0.0.1:3030");
-    warp::serve(image_proxy).run(([127, 0, 0, 1], 3030)).await;
+    let _ = PROXY_PORT.set(address.port());
+    tokio::spawn(server);
+    Ok(address.port())
 }
@@ -0,0 +1,169 @@
+use once_cell::sync::Lazy;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::redirect;
+use reqwest::Url;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::RwLock;
+
+use crate::config::load_config_or_default;
+
+// Hosts the proxy always fetches from, subdomains included
+const DEFAULT_HOSTS: &[&str] = &[
+    "steamstatic.com",
+    "steampowered.com",
+    "steamcommunity.com",
+    "steamcdn-a.akamaihd.net",
+    "steamuserimages-a.akamaihd.net",
+    "online-fix.me",
+];
+
+const MAX_REDIRECTS: usize = 5;
+
+// Origins of the app's webview, the only pages allowed to read proxy responses
+const APP_ORIGINS: &[&str] = &[
+    "tauri://localhost",
+    "http://tauri.localhost",
+    "https://tauri.localhost",
+    #[cfg(debug_assertions)]
+    "http://localhost:1420",
+];
+
+// Hosts added in the settings
+static EXTRA_HOSTS: Lazy<RwLock<Vec<String>>> =
+    Lazy::new(|| RwLock::new(load_config_or_default().proxy_hosts));
+
+pub fn set_extra_hosts(hosts: Vec<String>) {
+    *EXTRA_HOSTS.write().unwrap() = hosts;
+}
+
+fn host_allowed(host: &str) -> bool {
+    let host = host.trim_end_matches('.').to_lowercase();
+    let extra = EXTRA_HOSTS.read().unwrap();
+    DEFAULT_HOSTS
+        .iter()
+        .copied()
+        .chain(extra.iter().map(String::as_str))
+        .any(|allowed| host == allowed || host.ends_with(&format!(".{}", allowed)))
+}
+
+/// Whether the proxy may fetch `url`: plain http(s) on the default port to
+/// an allowlisted host name. IP literals are never allowed.
+pub fn check_url(url: &Url) -> Result<(), String> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("Scheme not allowed: {}", url.scheme()));
+    }
+    if url.port().is_some() {
+        return Err(format!("Port not allowed: {}", url));
+    }
+    // `domain` is unset for IP addresses
+    match url.domain() {
+        Some(domain) if host_allowed(domain) => Ok(()),
+        _ => Err(format!(
+            "Host not allowed: {}",
+            url.host_str().unwrap_or_default()
+        )),
+    }
+}
+
+pub fn is_app_origin(origin: &str) -> bool {
+    APP_ORIGINS.contains(&origin)
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            let shared = first == 100 && (second & 0xc0) == 64; // 100.64.0.0/10
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || first == 0
+                || shared)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public(IpAddr::V4(ip));
+            }
+            let first = ip.segments()[0];
+            let unique_local = (first & 0xfe00) == 0xfc00;
+            let link_local = (first & 0xffc0) == 0xfe80;
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || unique_local
+                || link_local)
+        }
+    }
+}
+
+/// System resolver that drops private, loopback and link-local addresses, so
+/// an allowed host name cannot point the proxy into the local network
+pub struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|addr| is_public(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no public address", host).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Follow at most a few redirects, each target must pass `check_url`
+pub fn redirect_policy() -> redirect::Policy {
+    redirect::Policy::custom(|attempt| {
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            attempt.error("Too many redirects")
+        } else if let Err(e) = check_url(attempt.url()) {
+            attempt.error(e)
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_local_addresses() {
+        for ip in [
+            "127.0.0.1",
+            "10.0.0.8",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "::1",
+            "fd00::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{}", ip);
+        }
+        assert!(is_public("23.45.67.89".parse().unwrap()));
+        assert!(is_public("2600:1400::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn rejects_ip_literals_and_other_schemes() {
+        for url in [
+            "http://127.0.0.1/a.jpg",
+            "http://[::1]/a.jpg",
+            "file:///etc/passwd",
+            "ftp://steamstatic.com/a.jpg",
+        ] {
+            assert!(check_url(&Url::parse(url).unwrap()).is_err(), "{}", url);
+        }
+    }
+}
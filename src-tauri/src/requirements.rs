@@ -0,0 +1,287 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use scraper::Html;
+use serde::{Deserialize, Serialize};
+
+use crate::hardware::Hardware;
+use crate::steamapi::PCRequirements;
+
+// Line breaks of the store HTML, every requirement sits on its own line
+static LINE_BREAK: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)<br\s*/?>|</li>|</p>").unwrap());
+static SIZE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)(\d+(?:[.,]\d+)?)\s*(tb|gb|mb|g|m)\b").unwrap());
+static CORE_COUNT: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)(\d+)[\s-]*cores?\b").unwrap());
+static NUMBER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\d+").unwrap());
+
+// Spelled-out core counts, "quad-core" and friends
+const CORE_WORDS: &[(&str, u32)] = &[
+    ("single", 1),
+    ("dual", 2),
+    ("triple", 3),
+    ("quad", 4),
+    ("hexa", 6),
+    ("six", 6),
+    ("octa", 8),
+    ("eight", 8),
+];
+
+// Highest DirectX version a current Windows ships with
+const MAX_DIRECTX: u32 = 12;
+
+/// One requirements block (minimum or recommended) split into fields. The
+/// text fields keep Steam's wording, the numeric ones are what a check needs.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SystemRequirements {
+    pub os: Option<String>,
+    pub processor: Option<String>,
+    pub memory: Option<String>,
+    pub graphics: Option<String>,
+    pub directx: Option<String>,
+    pub storage: Option<String>,
+    pub memory_mb: Option<u64>,
+    pub storage_mb: Option<u64>,
+    pub cpu_cores: Option<u32>,
+    pub directx_version: Option<u32>,
+}
+
+impl SystemRequirements {
+    /// Parse the HTML of a `pc_requirements` block, `None` if it has no
+    /// recognizable field
+    pub fn parse(html: &str) -> Option<Self> {
+        let text = LINE_BREAK.replace_all(html, "\n");
+        let text: String = Html::parse_fragment(&text).root_element().text().collect();
+
+        let mut requirements = Self::default();
+        for line in text.lines() {
+            let Some((label, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+            let field = match label.trim().to_lowercase() {
+                l if l.starts_with("os") => &mut requirements.os,
+                l if l.starts_with("processor") || l == "cpu" => &mut requirements.processor,
+                l if l.starts_with("memory") || l == "ram" => &mut requirements.memory,
+                l if l.starts_with("graphics") || l.starts_with("video") => {
+                    &mut requirements.graphics
+                }
+                l if l.starts_with("directx") => &mut requirements.directx,
+                l if l.starts_with("storage") || l.starts_with("hard d") => {
+                    &mut requirements.storage
+                }
+                _ => continue,
+            };
+            field.get_or_insert_with(|| value.to_string());
+        }
+
+        requirements.memory_mb = requirements.memory.as_deref().and_then(parse_size_mb);
+        requirements.storage_mb = requirements.storage.as_deref().and_then(parse_size_mb);
+        requirements.cpu_cores = requirements.processor.as_deref().and_then(parse_core_count);
+        requirements.directx_version = requirements
+            .directx
+            .as_deref()
+            .and_then(|v| NUMBER.find(v)?.as_str().parse().ok());
+
+        (requirements != Self::default()).then_some(requirements)
+    }
+}
+
+// First size in a requirement ("8 GB RAM", "500 MB available space") in MB
+fn parse_size_mb(value: &str) -> Option<u64> {
+    let caps = SIZE.captures(value)?;
+    let amount: f64 = caps[1].replace(',', ".").parse().ok()?;
+    let factor = match caps[2].to_lowercase().as_str() {
+        "tb" => 1024.0 * 1024.0,
+        "gb" | "g" => 1024.0,
+        _ => 1.0,
+    };
+    Some((amount * factor).round() as u64)
+}
+
+fn parse_core_count(value: &str) -> Option<u32> {
+    if let Some(caps) = CORE_COUNT.captures(value) {
+        return caps[1].parse().ok();
+    }
+    let lower = value.to_lowercase();
+    CORE_WORDS
+        .iter()
+        .find(|(word, _)| {
+            lower.contains(&format!("{}-core", word)) || lower.contains(&format!("{} core", word))
+        })
+        .map(|(_, cores)| *cores)
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Fail,
+    Unknown,
+}
+
+fn at_least(detected: Option<u64>, required: Option<u64>) -> CheckStatus {
+    match (detected, required) {
+        (Some(detected), Some(required)) if detected >= required => CheckStatus::Pass,
+        (Some(_), Some(_)) => CheckStatus::Fail,
+        _ => CheckStatus::Unknown,
+    }
+}
+
+/// How one field of the requirements compares to this machine
+#[derive(Serialize, Debug, Clone)]
+pub struct FieldCheck {
+    pub field: String,
+    pub required: Option<String>,
+    pub detected: Option<String>,
+    pub status: CheckStatus,
+}
+
+/// Compare one requirements block against the local hardware. GPU models
+/// cannot be ranked against each other, so graphics is always `unknown`.
+pub fn check_requirements(
+    requirements: &SystemRequirements,
+    hardware: &Hardware,
+) -> Vec<FieldCheck> {
+    let is_windows = hardware.os == "windows";
+    let is_64_bit = hardware.arch.contains("64");
+
+    let os_status = match requirements.os.as_deref() {
+        Some(os) if os.contains("64") && !is_64_bit => CheckStatus::Fail,
+        Some(os) if is_windows && os.to_lowercase().contains("windows") => CheckStatus::Pass,
+        // Other systems run Windows games through a compatibility layer
+        _ => CheckStatus::Unknown,
+    };
+
+    let directx_status = match requirements.directx_version {
+        Some(version) if is_windows && version <= MAX_DIRECTX => CheckStatus::Pass,
+        Some(_) if is_windows => CheckStatus::Fail,
+        _ => CheckStatus::Unknown,
+    };
+
+    let cpu_detected = match (&hardware.cpu_model, hardware.cpu_cores) {
+        (Some(model), Some(cores)) => Some(format!("{} ({} cores)", model, cores)),
+        (Some(model), None) => Some(model.clone()),
+        (None, Some(cores)) => Some(format!("{} cores", cores)),
+        (None, None) => None,
+    };
+
+    [
+        (
+            "os",
+            &requirements.os,
+            Some(format!("{} ({})", hardware.os, hardware.arch)),
+            os_status,
+        ),
+        (
+            "processor",
+            &requirements.processor,
+            cpu_detected,
+            at_least(
+                hardware.cpu_cores.map(u64::from),
+                requirements.cpu_cores.map(u64::from),
+            ),
+        ),
+        (
+            "memory",
+            &requirements.memory,
+            hardware.memory_mb.map(|mb| format!("{} MB", mb)),
+            at_least(hardware.memory_mb, requirements.memory_mb),
+        ),
+        (
+            "graphics",
+            &requirements.graphics,
+            hardware.gpu.clone(),
+            CheckStatus::Unknown,
+        ),
+        ("directx", &requirements.directx, None, directx_status),
+        (
+            "storage",
+            &requirements.storage,
+            hardware.free_disk_mb.map(|mb| format!("{} MB free", mb)),
+            at_least(hardware.free_disk_mb, requirements.storage_mb),
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, required, _, _)| required.is_some())
+    .map(|(field, required, detected, status)| FieldCheck {
+        field: field.to_string(),
+        required: required.clone(),
+        detected,
+        status,
+    })
+    .collect()
+}
+
+/// Result of `check_system_requirements`
+#[derive(Serialize, Debug, Clone)]
+pub struct RequirementsCheck {
+    pub hardware: Hardware,
+    pub minimum: Vec<FieldCheck>,
+    pub recommended: Vec<FieldCheck>,
+}
+
+impl RequirementsCheck {
+    pub fn new(requirements: &PCRequirements, hardware: Hardware) -> Self {
+        let check = |parsed: &Option<SystemRequirements>| {
+            parsed
+                .as_ref()
+                .map(|r| check_requirements(r, &hardware))
+                .unwrap_or_default()
+        };
+        Self {
+            minimum: check(&requirements.minimum_parsed),
+            recommended: check(&requirements.recommended_parsed),
+            hardware,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINIMUM: &str = r#"<strong>Minimum:</strong><br><ul class="bb_ul"><li>Requires a 64-bit processor and operating system<br></li><li><strong>OS:</strong> Windows 10 64-bit<br></li><li><strong>Processor:</strong> Intel Core i5-4430 / AMD FX-6300 quad-core<br></li><li><strong>Memory:</strong> 8 GB RAM<br></li><li><strong>Graphics:</strong> NVIDIA GeForce GTX 960 2GB<br></li><li><strong>DirectX:</strong> Version 11<br></li><li><strong>Storage:</strong> 1.5 TB available space</li></ul>"#;
+
+    #[test]
+    fn parses_store_html() {
+        let requirements = SystemRequirements::parse(MINIMUM).unwrap();
+
+        assert_eq!(requirements.os.as_deref(), Some("Windows 10 64-bit"));
+        assert_eq!(requirements.memory_mb, Some(8 * 1024));
+        assert_eq!(requirements.storage_mb, Some(1536 * 1024));
+        assert_eq!(requirements.cpu_cores, Some(4));
+        assert_eq!(requirements.directx_version, Some(11));
+        assert_eq!(
+            requirements.graphics.as_deref(),
+            Some("NVIDIA GeForce GTX 960 2GB")
+        );
+    }
+
+    #[test]
+    fn plain_text_without_fields_is_none() {
+        assert!(SystemRequirements::parse("<strong>Minimum:</strong><br>TBD").is_none());
+    }
+
+    #[test]
+    fn checks_against_hardware() {
+        let requirements = SystemRequirements::parse(MINIMUM).unwrap();
+        let hardware = Hardware {
+            os: "linux".to_string(),
+            arch: "x86_64".to_string(),
+            cpu_cores: Some(8),
+            memory_mb: Some(4096),
+            ..Default::default()
+        };
+
+        let checks = check_requirements(&requirements, &hardware);
+        let status = |field: &str| checks.iter().find(|c| c.field == field).unwrap().status;
+
+        assert_eq!(status("os"), CheckStatus::Unknown);
+        assert_eq!(status("processor"), CheckStatus::Pass);
+        assert_eq!(status("memory"), CheckStatus::Fail);
+        assert_eq!(status("graphics"), CheckStatus::Unknown);
+        assert_eq!(status("storage"), CheckStatus::Unknown);
+    }
+}
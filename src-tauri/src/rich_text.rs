@@ -0,0 +1,329 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::Url;
+use scraper::{ElementRef, Html, Node};
+use serde::Deserialize;
+
+use crate::proxy::proxied_url;
+
+// Tags kept in sanitized HTML, everything else is unwrapped to its content
+const ALLOWED_TAGS: &[&str] = &[
+    "p",
+    "br",
+    "b",
+    "strong",
+    "i",
+    "em",
+    "u",
+    "ul",
+    "ol",
+    "li",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "blockquote",
+    "img",
+    "a",
+    "video",
+    "source",
+    "span",
+    "div",
+    "hr",
+];
+
+// Tags dropped together with everything inside them
+const DROPPED_TAGS: &[&str] = &[
+    "script", "style", "iframe", "object", "embed", "noscript", "template", "form", "svg", "math",
+    "link", "meta",
+];
+
+const VOID_TAGS: &[&str] = &["br", "img", "source", "hr"];
+
+// Boolean attributes of embedded animations
+const VIDEO_FLAGS: &[&str] = &["autoplay", "muted", "loop", "playsinline", "controls"];
+
+// Query parameters that only exist to track clicks
+const TRACKING_PARAMS: &[&str] = &["fbclid", "gclid", "dclid", "msclkid", "mc_eid", "igshid"];
+
+static WHITESPACE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\s+").unwrap());
+static BLANK_LINES: Lazy<Regex> = Lazy::new(|| Regex::new(r"\n[ \t]*(\n[ \t]*)+").unwrap());
+
+/// Renditions of a store description
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TextFormat {
+    Html,
+    Markdown,
+    Text,
+}
+
+/// Store HTML reduced to a small allowlist of tags and attributes. Images
+/// and videos load through the local proxy and links lose Steam's link
+/// filter and tracking parameters.
+pub fn sanitize_html(html: &str) -> String {
+    let fragment = Html::parse_fragment(html);
+    let mut out = String::new();
+    write_html(fragment.root_element(), &mut out);
+    out
+}
+
+fn write_html(parent: ElementRef, out: &mut String) {
+    for child in parent.children() {
+        match child.value() {
+            Node::Text(text) => out.push_str(&escape(text)),
+            Node::Element(element) => {
+                let child = ElementRef::wrap(child).unwrap();
+                let name = element.name();
+                if DROPPED_TAGS.contains(&name) {
+                    continue;
+                }
+                if !ALLOWED_TAGS.contains(&name) {
+                    write_html(child, out);
+                    continue;
+                }
+                let Some(attributes) = allowed_attributes(name, |attr| element.attr(attr)) else {
+                    // Media without a usable source
+                    continue;
+                };
+
+                out.push('<');
+                out.push_str(name);
+                for (attr, value) in attributes {
+                    match value {
+                        Some(value) => out.push_str(&format!(" {}=\"{}\"", attr, escape(&value))),
+                        None => out.push_str(&format!(" {}", attr)),
+                    }
+                }
+                out.push('>');
+                if VOID_TAGS.contains(&name) {
+                    continue;
+                }
+                write_html(child, out);
+                out.push_str(&format!("</{}>", name));
+            }
+            _ => {}
+        }
+    }
+}
+
+// Attributes kept for a tag, `None` if the element must go. Attributes with
+// a `None` value are boolean.
+fn allowed_attributes<'a>(
+    tag: &str,
+    attr: impl Fn(&str) -> Option<&'a str>,
+) -> Option<Vec<(&'static str, Option<String>)>> {
+    let mut attributes = Vec::new();
+    match tag {
+        "a" => {
+            if let Some(href) = attr("href").and_then(clean_link) {
+                attributes.push(("href", Some(href)));
+                attributes.push(("target", Some("_blank".to_string())));
+                attributes.push(("rel", Some("noopener noreferrer nofollow".to_string())));
+            }
+        }
+        "img" => {
+            let src = attr("src").and_then(proxied_media)?;
+            attributes.push(("src", Some(src)));
+            if let Some(alt) = attr("alt") {
+                attributes.push(("alt", Some(alt.to_string())));
+            }
+        }
+        "video" => {
+            if let Some(poster) = attr("poster").and_then(proxied_media) {
+                attributes.push(("poster", Some(poster)));
+            }
+            for &flag in VIDEO_FLAGS {
+                if attr(flag).is_some() {
+                    attributes.push((flag, None));
+                }
+            }
+        }
+        "source" => {
+            let src = attr("src").and_then(proxied_media)?;
+            attributes.push(("src", Some(src)));
+            if let Some(media_type) = attr("type").filter(|t| t.starts_with("video/")) {
+                attributes.push(("type", Some(media_type.to_string())));
+            }
+        }
+        _ => {}
+    }
+    Some(attributes)
+}
+
+fn http_url(url: &str) -> Option<Url> {
+    let url = Url::parse(url.trim()).ok()?;
+    matches!(url.scheme(), "http" | "https").then_some(url)
+}
+
+fn proxied_media(src: &str) -> Option<String> {
+    http_url(src).map(|url| proxied_url(url.as_str()))
+}
+
+/// Link target without Steam's link filter and tracking parameters, `None`
+/// for anything but http(s)
+fn clean_link(href: &str) -> Option<String> {
+    let mut url = http_url(href)?;
+
+    // steamcommunity.com/linkfilter/?url=<target> or ?u=<target>
+    if url.host_str() == Some("steamcommunity.com") && url.path().starts_with("/linkfilter") {
+        let target = url
+            .query_pairs()
+            .find(|(key, _)| key == "url" || key == "u")
+            .map(|(_, value)| value.into_owned())?;
+        url = http_url(&target)?;
+    }
+
+    let kept: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| !key.starts_with("utm_") && !TRACKING_PARAMS.contains(&key.as_ref()))
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    if kept.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(kept);
+    }
+    Some(url.to_string())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Markdown rendition for compact views, media is left out. With `plain`
+/// only the text and line structure remain.
+pub fn to_markdown(html: &str, plain: bool) -> String {
+    let fragment = Html::parse_fragment(html);
+    let mut out = String::new();
+    write_markdown(fragment.root_element(), &mut out, plain);
+
+    let out = BLANK_LINES.replace_all(&out, "\n\n");
+    out.lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+fn write_markdown(parent: ElementRef, out: &mut String, plain: bool) {
+    for child in parent.children() {
+        match child.value() {
+            Node::Text(text) => {
+                let text = WHITESPACE.replace_all(text, " ");
+                // Leading spaces of a line carry no meaning
+                let text = if out.is_empty() || out.ends_with('\n') {
+                    text.trim_start()
+                } else {
+                    text.as_ref()
+                };
+                if plain {
+                    out.push_str(text);
+                } else {
+                    out.push_str(&escape_markdown(text));
+                }
+            }
+            Node::Element(element) => {
+                let child = ElementRef::wrap(child).unwrap();
+                let name = element.name();
+                if DROPPED_TAGS.contains(&name) || matches!(name, "img" | "video") {
+                    continue;
+                }
+                let (before, after) = match name {
+                    "br" => ("\n", ""),
+                    "p" | "div" | "blockquote" | "ul" | "ol" => ("\n\n", "\n\n"),
+                    "hr" if plain => ("\n\n", ""),
+                    "hr" => ("\n\n---\n\n", ""),
+                    "li" if plain => ("\n", ""),
+                    "li" => ("\n- ", ""),
+                    "h1" | "h2" | "h3" | "h4" | "h5" | "h6" if plain => ("\n\n", "\n\n"),
+                    "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => ("\n\n## ", "\n\n"),
+                    "b" | "strong" if !plain => ("**", "**"),
+                    "i" | "em" if !plain => ("_", "_"),
+                    _ => ("", ""),
+                };
+
+                if name == "a" && !plain {
+                    let mut text = String::new();
+                    write_markdown(child, &mut text, plain);
+                    match element.attr("href").and_then(clean_link) {
+                        Some(href) if !text.trim().is_empty() => {
+                            out.push_str(&format!("[{}]({})", text.trim(), href))
+                        }
+                        _ => out.push_str(&text),
+                    }
+                    continue;
+                }
+
+                out.push_str(before);
+                write_markdown(child, out, plain);
+                out.push_str(after);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '*' | '_' | '[' | ']' | '`' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_scripts_and_unknown_attributes() {
+        let html = r#"<p class="bb_paragraph" onclick="steal()">Hello <script>alert(1)</script><b style="color:red">world</b></p><iframe src="https://example.com"></iframe>"#;
+
+        assert_eq!(sanitize_html(html), "<p>Hello <b>world</b></p>");
+    }
+
+    #[test]
+    fn proxies_images_and_drops_unsafe_ones() {
+        let html = r#"<img src="https://cdn.akamai.steamstatic.com/a.gif"><img src="javascript:alert(1)">"#;
+        let sanitized = sanitize_html(html);
+
+        assert!(sanitized.starts_with(r#"<img src="http://127.0.0.1:"#));
+        assert!(sanitized.contains("a.gif"));
+        assert_eq!(sanitized.matches("<img").count(), 1);
+    }
+
+    #[test]
+    fn unwraps_link_filter_and_tracking() {
+        let href = "https://steamcommunity.com/linkfilter/?url=https://example.com/page?utm_source=steam%26id=3";
+
+        assert_eq!(
+            clean_link(href).as_deref(),
+            Some("https://example.com/page?id=3")
+        );
+        assert_eq!(clean_link("javascript:alert(1)"), None);
+    }
+
+    #[test]
+    fn renders_markdown_and_text() {
+        let html = "<h2>About</h2><p>Build <strong>big</strong> bases.<br>Survive.</p><ul><li>Co-op</li><li>Crafting</li></ul>";
+
+        assert_eq!(
+            to_markdown(html, false),
+            "## About\n\nBuild **big** bases.\nSurvive.\n\n- Co-op\n- Crafting"
+        );
+        assert_eq!(
+            to_markdown(html, true),
+            "About\n\nBuild big bases.\nSurvive.\n\nCo-op\nCrafting"
+        );
+    }
+}
@@ -0,0 +1,355 @@
+use anyhow::Context;
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::config::load_config_or_default;
+
+// File inside every backup that lists the original save folders
+const LOCATIONS_FILE: &str = "locations.json";
+
+// Where games usually keep their saves, `{game}` is replaced with the game name
+const DEFAULT_SAVE_RULES: &[&str] = &[
+    "%APPDATA%/{game}",
+    "%LOCALAPPDATA%/{game}",
+    "%DOCUMENTS%/My Games/{game}",
+    "%USERPROFILE%/Saved Games/{game}",
+    "%GAME_DIR%/Saves",
+    "%GAME_DIR%/saves",
+];
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SaveBackup {
+    pub game_name: String,
+    pub file_name: String,
+    pub created_at: u64, // Unix timestamp in seconds
+    pub size: u64,
+}
+
+// Directory holding all backups for one game
+fn backups_dir(game_name: &str) -> PathBuf {
+    let base_dir = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
+    let safe_name: String = game_name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == ' ' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    base_dir
+        .join("PirateLand")
+        .join("save_backups")
+        .join(safe_name)
+}
+
+/// Base folders a placeholder can point to. On Windows these come straight
+/// from the environment, elsewhere they point inside the Wine prefixes.
+fn placeholder_roots(name: &str) -> Vec<PathBuf> {
+    if cfg!(target_os = "windows") {
+        let from_env = |var: &str| std::env::var(var).ok().map(PathBuf::from);
+        let root = match name {
+            "APPDATA" => from_env("APPDATA"),
+            "LOCALAPPDATA" => from_env("LOCALAPPDATA"),
+            "USERPROFILE" => from_env("USERPROFILE"),
+            "DOCUMENTS" => dirs::document_dir(),
+            _ => None,
+        };
+        return root.into_iter().collect();
+    }
+
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    let prefix = std::env::var("WINEPREFIX")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| home.join(".wine"));
+    let user = std::env::var("USER").unwrap_or_else(|_| "user".to_string());
+
+    // Proton prefixes always use `steamuser`
+    let mut user_dirs = vec![prefix.join("drive_c/users").join(&user)];
+    if user != "steamuser" {
+        user_dirs.push(prefix.join("drive_c/users/steamuser"));
+    }
+
+    user_dirs
+        .into_iter()
+        .filter_map(|user_dir| match name {
+            "APPDATA" => Some(user_dir.join("AppData/Roaming")),
+            "LOCALAPPDATA" => Some(user_dir.join("AppData/Local")),
+            "USERPROFILE" => Some(user_dir),
+            "DOCUMENTS" => Some(user_dir.join("Documents")),
+            _ => None,
+        })
+        .collect()
+}
+
+// Expand a single rule into every concrete path it can refer to
+fn expand_rule(rule: &str, game_name: &str, game_dir: Option<&Path>) -> Vec<PathBuf> {
+    let rule = rule.replace("{game}", game_name);
+
+    let Some(rest) = rule.strip_prefix('%') else {
+        return vec![PathBuf::from(rule)];
+    };
+    let Some((name, tail)) = rest.split_once('%') else {
+        return vec![PathBuf::from(rule)];
+    };
+    let tail = tail.trim_start_matches(['/', '\\']);
+
+    let roots = if name == "GAME_DIR" {
+        game_dir.map(Path::to_path_buf).into_iter().collect()
+    } else {
+        placeholder_roots(name)
+    };
+
+    roots.into_iter().map(|root| root.join(tail)).collect()
+}
+
+/// Existing save folders for a game. A manual override in the config replaces
+/// the default rules entirely.
+pub fn resolve_save_locations(game_name: &str, game_dir: Option<&Path>) -> Vec<PathBuf> {
+    let config = load_config_or_default();
+    let rules: Vec<String> = match config.save_locations.get(game_name) {
+        Some(overrides) if !overrides.is_empty() => overrides.clone(),
+        _ => DEFAULT_SAVE_RULES.iter().map(|r| r.to_string()).collect(),
+    };
+
+    let mut locations = Vec::new();
+    for rule in &rules {
+        for path in expand_rule(rule, game_name, game_dir) {
+            if path.is_dir() && !locations.contains(&path) {
+                locations.push(path);
+            }
+        }
+    }
+    locations
+}
+
+/// Write a timestamped zip snapshot of every save folder of the game.
+/// Returns `None` when no save folder could be found.
+pub fn backup_saves(
+    game_name: &str,
+    game_dir: Option<&Path>,
+) -> anyhow::Result<Option<SaveBackup>> {
+    let locations = resolve_save_locations(game_name, game_dir);
+    if locations.is_empty() {
+        log::info!("[SAVES] No save folders found for {}", game_name);
+        return Ok(None);
+    }
+
+    let backup_dir = backups_dir(game_name);
+    fs::create_dir_all(&backup_dir)?;
+
+    let created_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let (file_name, file) = create_backup_file(&backup_dir, created_at)?;
+    let backup_path = backup_dir.join(&file_name);
+
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default();
+
+    let original_paths: Vec<String> = locations
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+    zip.start_file(LOCATIONS_FILE, options)?;
+    zip.write_all(serde_json::to_string_pretty(&original_paths)?.as_bytes())?;
+
+    // Every location gets its own numbered folder inside the archive
+    for (index, location) in locations.iter().enumerate() {
+        add_dir_to_zip(&mut zip, location, Path::new(&index.to_string()), options)?;
+    }
+    zip.finish()?;
+
+    let size = fs::metadata(&backup_path)?.len();
+    log::info!(
+        "[SAVES] Backed up saves of {} to {}",
+        game_name,
+        backup_path.display()
+    );
+
+    Ok(Some(SaveBackup {
+        game_name: game_name.to_string(),
+        file_name,
+        created_at,
+        size,
+    }))
+}
+
+// New archive for a backup taken at `created_at`. Backups within the same
+// second get a counter (`<stamp>_<n>.zip`), an existing one is never reopened.
+fn create_backup_file(backup_dir: &Path, created_at: u64) -> io::Result<(String, File)> {
+    let mut counter = 0;
+    loop {
+        let file_name = match counter {
+            0 => format!("{}.zip", created_at),
+            n => format!("{}_{}.zip", created_at, n),
+        };
+        let opened = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(backup_dir.join(&file_name));
+        match opened {
+            Ok(file) => return Ok((file_name, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => counter += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// Timestamp and same-second counter of a backup file name
+fn parse_backup_name(file_name: &str) -> Option<(u64, u32)> {
+    let stem = file_name.strip_suffix(".zip")?;
+    match stem.split_once('_') {
+        Some((stamp, counter)) => Some((stamp.parse().ok()?, counter.parse().ok()?)),
+        None => Some((stem.parse().ok()?, 0)),
+    }
+}
+
+fn add_dir_to_zip(
+    zip: &mut ZipWriter<File>,
+    dir: &Path,
+    prefix: &Path,
+    options: SimpleFileOptions,
+) -> anyhow::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = prefix.join(path.file_name().unwrap_or_default());
+        // Zip entries always use forward slashes
+        let entry_name = name.to_string_lossy().replace('\\', "/");
+
+        if path.is_dir() {
+            zip.add_directory(entry_name, options)?;
+            add_dir_to_zip(zip, &path, &name, options)?;
+        } else {
+            zip.start_file(entry_name, options)?;
+            let mut file = File::open(&path)?;
+            io::copy(&mut file, zip)?;
+        }
+    }
+    Ok(())
+}
+
+/// All backups of a game, newest first
+pub fn list_backups(game_name: &str) -> anyhow::Result<Vec<SaveBackup>> {
+    let backup_dir = backups_dir(game_name);
+    if !backup_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(&backup_dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let Some((created_at, _)) = parse_backup_name(&file_name) else {
+            continue;
+        };
+        backups.push(SaveBackup {
+            game_name: game_name.to_string(),
+            file_name,
+            created_at,
+            size: entry.metadata()?.len(),
+        });
+    }
+
+    backups.sort_by_key(|b| std::cmp::Reverse(parse_backup_name(&b.file_name)));
+    Ok(backups)
+}
+
+/// Put a backup back into the folders it was taken from. The current saves
+/// are backed up first so a restore can always be undone.
+pub fn restore_saves(
+    game_name: &str,
+    file_name: &str,
+    game_dir: Option<&Path>,
+) -> anyhow::Result<()> {
+    if file_name.contains(['/', '\\']) {
+        anyhow::bail!("Invalid backup name: {}", file_name);
+    }
+    let backup_path = backups_dir(game_name).join(file_name);
+    let file = File::open(&backup_path)
+        .with_context(|| format!("Backup not found: {}", backup_path.display()))?;
+    let mut archive = ZipArchive::new(file)?;
+
+    let original_paths: Vec<String> = {
+        let mut locations_file = archive.by_name(LOCATIONS_FILE)?;
+        let mut content = String::new();
+        locations_file.read_to_string(&mut content)?;
+        serde_json::from_str(&content)?
+    };
+
+    backup_saves(game_name, game_dir)?;
+
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        let Some(name) = entry.enclosed_name() else {
+            continue;
+        };
+
+        // First component is the index of the original location
+        let mut components = name.components();
+        let Some(Component::Normal(index)) = components.next() else {
+            continue;
+        };
+        let Some(target_root) = index
+            .to_str()
+            .and_then(|i| i.parse::<usize>().ok())
+            .and_then(|i| original_paths.get(i))
+        else {
+            continue;
+        };
+        let target = Path::new(target_root).join(components.as_path());
+
+        if entry.is_dir() {
+            fs::create_dir_all(&target)?;
+        } else {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut out = File::create(&target)?;
+            io::copy(&mut entry, &mut out)?;
+        }
+    }
+
+    log::info!("[SAVES] Restored {} for {}", file_name, game_name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backups_in_the_same_second_get_distinct_files() {
+        let dir = std::env::temp_dir().join(format!("pirateland-saves-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let (first, mut file) = create_backup_file(&dir, 1_700_000_000).unwrap();
+        file.write_all(b"first").unwrap();
+        let (second, _) = create_backup_file(&dir, 1_700_000_000).unwrap();
+
+        assert_eq!(first, "1700000000.zip");
+        assert_eq!(second, "1700000000_1.zip");
+        // The earlier archive is left untouched
+        assert_eq!(fs::read(dir.join(&first)).unwrap(), b"first");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parses_backup_names() {
+        assert_eq!(
+            parse_backup_name("1700000000.zip"),
+            Some((1_700_000_000, 0))
+        );
+        assert_eq!(
+            parse_backup_name("1700000000_2.zip"),
+            Some((1_700_000_000, 2))
+        );
+        assert_eq!(parse_backup_name("notes.txt"), None);
+        assert!(parse_backup_name("1700000000_2.zip") > parse_backup_name("1700000000_1.zip"));
+    }
+}
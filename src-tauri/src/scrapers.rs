@@ -3,8 +3,6 @@ use scraper::{Html, Selector};
 use serde::Serialize;
 use std::error::Error;
 
-use crate::STEAM_GAME_STORE_INDEX;
-
 #[derive(Serialize)]
 pub struct Game {
     pub title: String,
@@ -0,0 +1,414 @@
+use once_cell::sync::Lazy;
+use reqwest::{Client, StatusCode};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, Mutex};
+
+use crate::steamapi::Locale;
+
+// Timeout for downloading the full app list
+const APP_LIST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Shared client for all store API calls
+pub static STEAM_CLIENT: Lazy<SteamClient> =
+    Lazy::new(|| SteamClient::new(SteamClientOptions::default()));
+
+pub struct SteamClientOptions {
+    /// Store API, serves `appdetails`
+    pub base_url: String,
+    /// Web API, serves the app list
+    pub api_base_url: String,
+    /// Requests that can be sent in a burst
+    pub burst: f64,
+    /// Sustained request rate once the burst is used up
+    pub requests_per_sec: f64,
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled on every further attempt
+    pub base_backoff: Duration,
+    pub max_backoff: Duration,
+    pub timeout: Duration,
+}
+
+impl Default for SteamClientOptions {
+    fn default() -> Self {
+        // The store API allows roughly 200 requests per 5 minutes
+        Self {
+            base_url: "https://store.steampowered.com".to_string(),
+            api_base_url: "https://api.steampowered.com".to_string(),
+            burst: 10.0,
+            requests_per_sec: 0.6,
+            max_retries: 4,
+            base_backoff: Duration::from_secs(2),
+            max_backoff: Duration::from_secs(60),
+            timeout: Duration::from_secs(15),
+        }
+    }
+}
+
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(capacity: f64, refill_per_sec: f64) -> Self {
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_per_sec,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Takes a token, or returns how long to wait until one is available
+    fn try_take(&mut self) -> Result<(), Duration> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            let missing = 1.0 - self.tokens;
+            Err(Duration::from_secs_f64(missing / self.refill_per_sec))
+        }
+    }
+}
+
+type AppDetailsResult = Result<serde_json::Value, String>;
+type InFlightKey = (u32, Locale);
+
+// Clears the in-flight entry if the leading request is dropped before it
+// finishes, so waiting callers get an error instead of hanging
+struct InFlightGuard<'a> {
+    client: &'a SteamClient,
+    key: InFlightKey,
+    published: bool,
+}
+
+impl InFlightGuard<'_> {
+    // Send the result to the waiting callers and retire the entry. Holding
+    // the lock means no subscriber can miss the result, and the guard is
+    // disarmed so it never removes an entry of a later leader.
+    fn publish(&mut self, result: &AppDetailsResult) {
+        self.published = true;
+        let mut in_flight = self.client.in_flight.lock().unwrap();
+        if let Some(sender) = in_flight.remove(&self.key) {
+            let _ = sender.send(result.clone());
+        }
+    }
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        if !self.published {
+            self.client.in_flight.lock().unwrap().remove(&self.key);
+        }
+    }
+}
+
+pub struct SteamClient {
+    client: Client,
+    options: SteamClientOptions,
+    limiter: Mutex<TokenBucket>,
+    // Lookups currently on the wire, later callers wait for the same result
+    in_flight: std::sync::Mutex<HashMap<InFlightKey, broadcast::Sender<AppDetailsResult>>>,
+}
+
+impl SteamClient {
+    pub fn new(options: SteamClientOptions) -> Self {
+        let client = Client::builder()
+            .timeout(options.timeout)
+            .build()
+            .unwrap_or_else(|_| Client::new());
+
+        Self {
+            client,
+            limiter: Mutex::new(TokenBucket::new(options.burst, options.requests_per_sec)),
+            options,
+            in_flight: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn wait_for_token(&self) {
+        loop {
+            let wait = match self.limiter.lock().await.try_take() {
+                Ok(()) => return,
+                Err(wait) => wait,
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt);
+        self.options
+            .base_backoff
+            .saturating_mul(factor)
+            .min(self.options.max_backoff)
+    }
+
+    /// Raw `appdetails` entry for an appid (`{"success": .., "data": ..}`).
+    ///
+    /// Concurrent calls for the same appid and locale share one request.
+    pub async fn app_details(&self, appid: u32, locale: &Locale) -> AppDetailsResult {
+        let key = (appid, locale.clone());
+        let mut receiver = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            match in_flight.get(&key) {
+                Some(sender) => Some(sender.subscribe()),
+                None => {
+                    let (sender, _) = broadcast::channel(1);
+                    in_flight.insert(key.clone(), sender);
+                    None
+                }
+            }
+        };
+
+        if let Some(receiver) = receiver.as_mut() {
+            return receiver
+                .recv()
+                .await
+                .map_err(|e| format!("Shared request for {} failed: {}", appid, e))?;
+        }
+
+        let mut guard = InFlightGuard {
+            client: self,
+            key,
+            published: false,
+        };
+        let result = self.request_app_details(appid, locale).await;
+        guard.publish(&result);
+        result
+    }
+
+    async fn request_app_details(&self, appid: u32, locale: &Locale) -> AppDetailsResult {
+        let url = format!(
+            "{}/api/appdetails?appids={}{}",
+            self.options.base_url,
+            appid,
+            locale.query()
+        );
+        let what = format!("appid {}", appid);
+        let response = self.send_with_retries(&url, &what, None).await?;
+
+        let mut json: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| format!("Invalid appdetails response: {}", e))?;
+        Ok(json[appid.to_string()].take())
+    }
+
+    /// Every app on Steam, as returned by `ISteamApps/GetAppList`
+    /// (`{"applist": {"apps": [{"appid": .., "name": ..}]}}`)
+    pub async fn app_list(&self) -> Result<bytes::Bytes, String> {
+        let url = format!("{}/ISteamApps/GetAppList/v2/", self.options.api_base_url);
+        // The full list is tens of megabytes, far beyond a normal response
+        let response = self
+            .send_with_retries(&url, "the app list", Some(APP_LIST_TIMEOUT))
+            .await?;
+
+        response
+            .bytes()
+            .await
+            .map_err(|e| format!("Failed to read the app list: {}", e))
+    }
+
+    // GET through the rate limiter, retrying rate limits, server errors and
+    // network failures with backoff. `what` names the resource in errors.
+    async fn send_with_retries(
+        &self,
+        url: &str,
+        what: &str,
+        timeout: Option<Duration>,
+    ) -> Result<reqwest::Response, String> {
+        let mut attempt = 0;
+        loop {
+            self.wait_for_token().await;
+
+            let mut request = self.client.get(url);
+            if let Some(timeout) = timeout {
+                request = request.timeout(timeout);
+            }
+
+            let retry_after = match request.send().await {
+                Ok(response) => {
+                    let status = response.status();
+                    if status.is_success() {
+                        return Ok(response);
+                    }
+                    if status != StatusCode::TOO_MANY_REQUESTS && !status.is_server_error() {
+                        return Err(format!("Steam API returned {} for {}", status, what));
+                    }
+                    log::warn!("[STEAM] {} for {}, attempt {}", status, what, attempt + 1);
+                    if attempt >= self.options.max_retries {
+                        return Err(format!("Steam API returned {} for {}", status, what));
+                    }
+                    response
+                        .headers()
+                        .get(reqwest::header::RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.parse::<u64>().ok())
+                        .map(Duration::from_secs)
+                }
+                Err(e) => {
+                    log::warn!("[STEAM] Request for {} failed: {}", what, e);
+                    if attempt >= self.options.max_retries {
+                        return Err(format!("Request for {} failed: {}", what, e));
+                    }
+                    None
+                }
+            };
+
+            let delay = retry_after
+                .map(|d| d.min(self.options.max_backoff))
+                .unwrap_or_else(|| self.backoff(attempt));
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use warp::Filter;
+
+    fn test_options(base_url: String) -> SteamClientOptions {
+        SteamClientOptions {
+            api_base_url: base_url.clone(),
+            base_url,
+            burst: 100.0,
+            requests_per_sec: 100.0,
+            max_retries: 3,
+            base_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(50),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Mock store API answering with `failures` errors before succeeding
+    fn start_mock(failures: usize, status: u16, delay: Duration) -> (String, Arc<AtomicUsize>) {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+
+        let route = warp::path!("api" / "appdetails")
+            .and(warp::query::<HashMap<String, String>>())
+            .and_then(move |params: HashMap<String, String>| {
+                let counter = counter.clone();
+                async move {
+                    let hit = counter.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(delay).await;
+                    let appid = params.get("appids").cloned().unwrap_or_default();
+                    let body = serde_json::json!({
+                        appid: { "success": true, "data": { "name": "Test Game" } }
+                    });
+                    let status = if hit < failures {
+                        warp::http::StatusCode::from_u16(status).unwrap()
+                    } else {
+                        warp::http::StatusCode::OK
+                    };
+                    Ok::<_, std::convert::Infallible>(warp::reply::with_status(
+                        warp::reply::json(&body),
+                        status,
+                    ))
+                }
+            });
+
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        (format!("http://{}", addr), hits)
+    }
+
+    #[tokio::test]
+    async fn retries_after_rate_limit() {
+        let (base_url, hits) = start_mock(2, 429, Duration::ZERO);
+        let client = SteamClient::new(test_options(base_url));
+
+        let details = client.app_details(10, &Locale::default()).await.unwrap();
+
+        assert_eq!(details["data"]["name"], "Test Game");
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let (base_url, hits) = start_mock(usize::MAX, 503, Duration::ZERO);
+        let client = SteamClient::new(test_options(base_url));
+
+        assert!(client.app_details(10, &Locale::default()).await.is_err());
+        assert_eq!(hits.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_client_errors() {
+        let (base_url, hits) = start_mock(usize::MAX, 404, Duration::ZERO);
+        let client = SteamClient::new(test_options(base_url));
+
+        assert!(client.app_details(10, &Locale::default()).await.is_err());
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn concurrent_lookups_share_one_request() {
+        let (base_url, hits) = start_mock(0, 200, Duration::from_millis(100));
+        let client = Arc::new(SteamClient::new(test_options(base_url)));
+
+        let lookups = (0..5).map(|_| {
+            let client = client.clone();
+            tokio::spawn(async move { client.app_details(42, &Locale::default()).await })
+        });
+        for lookup in lookups.collect::<Vec<_>>() {
+            assert!(lookup.await.unwrap().is_ok());
+        }
+
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn published_guard_leaves_a_new_leader_alone() {
+        let client = SteamClient::new(test_options("http://127.0.0.1:9".to_string()));
+        let key = (7, Locale::default());
+        let register = || {
+            let (sender, _) = broadcast::channel(1);
+            client.in_flight.lock().unwrap().insert(key.clone(), sender);
+        };
+
+        register();
+        let mut guard = InFlightGuard {
+            client: &client,
+            key: key.clone(),
+            published: false,
+        };
+        guard.publish(&Ok(serde_json::Value::Null));
+        // A later lookup becomes the leader before the old guard goes away
+        register();
+        drop(guard);
+
+        assert!(client.in_flight.lock().unwrap().contains_key(&key));
+    }
+
+    #[tokio::test]
+    async fn limiter_spaces_requests_after_burst() {
+        let (base_url, _) = start_mock(0, 200, Duration::ZERO);
+        let client = SteamClient::new(SteamClientOptions {
+            burst: 1.0,
+            requests_per_sec: 10.0,
+            ..test_options(base_url)
+        });
+
+        let started = Instant::now();
+        for appid in 0..3 {
+            client.app_details(appid, &Locale::default()).await.unwrap();
+        }
+
+        // One request from the burst, then two more at 100ms intervals
+        assert!(started.elapsed() >= Duration::from_millis(180));
+    }
+}
@@ -0,0 +1,217 @@
+use image::imageops::FilterType;
+use image::{GenericImageView, ImageFormat};
+use std::collections::HashMap;
+use std::io::Cursor;
+
+// Largest edge a resized image may have
+const MAX_EDGE: u32 = 2048;
+
+/// How a resized image fills the requested box
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fit {
+    /// Fit inside the box, keeping the aspect ratio
+    Contain,
+    /// Cover the box and crop what overflows
+    Cover,
+    /// Stretch to exactly the box
+    Fill,
+}
+
+/// Encoding of a resized image, the source's own format or WebP.
+///
+/// The WebP encoder is lossless only, which beats PNG but makes photos far
+/// larger than JPEG, so JPEG sources stay JPEG whatever is asked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Original,
+    Webp,
+}
+
+/// Resize requested through the proxy's `w`, `h`, `fit` and `format` query
+/// parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResizeOptions {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fit: Fit,
+    pub format: OutputFormat,
+}
+
+fn parse_fit(value: Option<&String>) -> Result<Fit, String> {
+    match value.map(|v| v.to_lowercase()).as_deref() {
+        None | Some("contain") => Ok(Fit::Contain),
+        Some("cover") => Ok(Fit::Cover),
+        Some("fill") => Ok(Fit::Fill),
+        Some(other) => Err(format!("Invalid 'fit': {}", other)),
+    }
+}
+
+fn parse_format(value: Option<&String>) -> Result<OutputFormat, String> {
+    match value.map(|v| v.to_lowercase()).as_deref() {
+        None | Some("original") => Ok(OutputFormat::Original),
+        Some("webp") => Ok(OutputFormat::Webp),
+        Some(other) => Err(format!("Invalid 'format': {}", other)),
+    }
+}
+
+fn parse_edge(params: &HashMap<String, String>, name: &str) -> Result<Option<u32>, String> {
+    let Some(value) = params.get(name) else {
+        return Ok(None);
+    };
+    match value.parse::<u32>() {
+        Ok(edge) if (1..=MAX_EDGE).contains(&edge) => Ok(Some(edge)),
+        _ => Err(format!("Invalid '{}': {}", name, value)),
+    }
+}
+
+impl ResizeOptions {
+    /// Options from the query, `None` when neither `w` nor `h` is given
+    pub fn from_query(params: &HashMap<String, String>) -> Result<Option<Self>, String> {
+        let options = Self {
+            width: parse_edge(params, "w")?,
+            height: parse_edge(params, "h")?,
+            fit: parse_fit(params.get("fit"))?,
+            format: parse_format(params.get("format"))?,
+        };
+        Ok((options.width.is_some() || options.height.is_some()).then_some(options))
+    }
+
+    /// Distinguishes cached variants of one image
+    pub fn key(&self) -> String {
+        format!(
+            "w={}&h={}&fit={:?}&format={:?}",
+            self.width.unwrap_or(0),
+            self.height.unwrap_or(0),
+            self.fit,
+            self.format
+        )
+        .to_lowercase()
+    }
+
+    // Box to resize into, a missing edge follows the aspect ratio
+    fn target(&self, width: u32, height: u32) -> (u32, u32) {
+        let scaled = |edge: u32, from: u32, to: u32| {
+            ((edge as u64 * to as u64) / from.max(1) as u64).max(1) as u32
+        };
+        match (self.width, self.height) {
+            (Some(w), Some(h)) => (w, h),
+            (Some(w), None) => (w, scaled(height, width, w)),
+            (None, Some(h)) => (scaled(width, height, h), h),
+            (None, None) => (width, height),
+        }
+    }
+}
+
+/// `data` resized per `options` with its content type, `None` when the image
+/// is left as it is: already small enough, possibly animated or unreadable
+pub fn resize(data: &[u8], options: &ResizeOptions) -> Option<(Vec<u8>, &'static str)> {
+    let source_format = image::guess_format(data).ok()?;
+    // Resizing would keep only the first frame
+    if source_format == ImageFormat::Gif {
+        return None;
+    }
+    let image = image::load_from_memory_with_format(data, source_format).ok()?;
+
+    let (width, height) = image.dimensions();
+    let (target_width, target_height) = options.target(width, height);
+    // Never upscale, the webview does that just as well
+    if target_width >= width && target_height >= height {
+        return None;
+    }
+
+    let resized = match options.fit {
+        Fit::Contain => image.resize(target_width, target_height, FilterType::CatmullRom),
+        Fit::Cover => image.resize_to_fill(target_width, target_height, FilterType::CatmullRom),
+        Fit::Fill => image.resize_exact(target_width, target_height, FilterType::CatmullRom),
+    };
+
+    let (format, content_type) = match (options.format, source_format) {
+        (_, ImageFormat::Jpeg) => (ImageFormat::Jpeg, "image/jpeg"),
+        (OutputFormat::Webp, _) | (_, ImageFormat::WebP) => (ImageFormat::WebP, "image/webp"),
+        // Anything else is re-encoded losslessly
+        _ => (ImageFormat::Png, "image/png"),
+    };
+    // JPEG has no alpha channel
+    let resized = match format {
+        ImageFormat::Jpeg => image::DynamicImage::ImageRgb8(resized.to_rgb8()),
+        _ => resized,
+    };
+
+    let mut out = Vec::new();
+    resized.write_to(&mut Cursor::new(&mut out), format).ok()?;
+    Some((out, content_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+        let image = image::RgbImage::from_pixel(width, height, image::Rgb([200, 30, 30]));
+        let mut out = Vec::new();
+        image.write_to(&mut Cursor::new(&mut out), format).unwrap();
+        out
+    }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        encoded(width, height, ImageFormat::Png)
+    }
+
+    fn options(query: &[(&str, &str)]) -> Option<ResizeOptions> {
+        let params = query
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        ResizeOptions::from_query(&params).unwrap()
+    }
+
+    #[test]
+    fn parses_query() {
+        assert_eq!(options(&[("url", "x")]), None);
+        let parsed = options(&[("w", "300"), ("fit", "Cover"), ("format", "webp")]).unwrap();
+        assert_eq!(parsed.width, Some(300));
+        assert_eq!(parsed.fit, Fit::Cover);
+        assert_eq!(parsed.format, OutputFormat::Webp);
+
+        let params = [("w".to_string(), "0".to_string())].into_iter().collect();
+        assert!(ResizeOptions::from_query(&params).is_err());
+    }
+
+    #[test]
+    fn resizes_by_fit() {
+        let data = png(400, 200);
+        let size = |query: &[(&str, &str)]| {
+            let (out, _) = resize(&data, &options(query).unwrap()).unwrap();
+            image::load_from_memory(&out).unwrap().dimensions()
+        };
+
+        assert_eq!(size(&[("w", "100")]), (100, 50));
+        assert_eq!(size(&[("w", "100"), ("h", "100")]), (100, 50));
+        assert_eq!(
+            size(&[("w", "100"), ("h", "100"), ("fit", "cover")]),
+            (100, 100)
+        );
+        assert_eq!(
+            size(&[("w", "100"), ("h", "100"), ("fit", "fill")]),
+            (100, 100)
+        );
+    }
+
+    #[test]
+    fn webp_only_replaces_lossless_sources() {
+        let webp = options(&[("w", "100"), ("format", "webp")]).unwrap();
+
+        let (_, content_type) = resize(&png(400, 200), &webp).unwrap();
+        assert_eq!(content_type, "image/webp");
+
+        let jpeg = encoded(400, 200, ImageFormat::Jpeg);
+        let (out, content_type) = resize(&jpeg, &webp).unwrap();
+        assert_eq!(content_type, "image/jpeg");
+        assert_eq!(image::guess_format(&out).unwrap(), ImageFormat::Jpeg);
+    }
+
+    #[test]
+    fn keeps_small_images() {
+        assert!(resize(&png(80, 40), &options(&[("w", "200")]).unwrap()).is_none());
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use strsim::damerau_levenshtein;
+
+// Upper bound of vocabulary words a single prefix may expand to
+const MAX_PREFIX_EXPANSIONS: usize = 64;
+
+// Words that only describe where a title was downloaded from
+const IGNORED_TOKENS: &[&str] = &["onlinefix", "crack", "repack"];
+
+// Roman numerals used in sequel titles, single letters are too ambiguous
+const ROMAN_NUMERALS: &[(&str, &str)] = &[
+    ("ii", "2"),
+    ("iii", "3"),
+    ("iv", "4"),
+    ("vi", "6"),
+    ("vii", "7"),
+    ("viii", "8"),
+    ("ix", "9"),
+];
+
+fn normalize_numeral(word: &str) -> String {
+    match ROMAN_NUMERALS.iter().find(|(roman, _)| *roman == word) {
+        Some((_, number)) => number.to_string(),
+        None => word.to_string(),
+    }
+}
+
+/// Split a title into lowercase alphanumeric words
+pub fn tokenize(title: &str) -> Vec<String> {
+    let lower = title
+        .to_lowercase()
+        .replace("online-fix", " ")
+        .replace("online fix", " ");
+    lower
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty() && !IGNORED_TOKENS.contains(t))
+        .map(normalize_numeral)
+        .collect()
+}
+
+// Trigrams of a word padded with start and end markers
+fn trigrams(word: &str) -> Vec<String> {
+    let chars: Vec<char> = format!("^{}$", word).chars().collect();
+    chars.windows(3).map(|w| w.iter().collect()).collect()
+}
+
+/// Inverted index from words to entries, with a trigram index over the
+/// vocabulary for typo-tolerant lookups. `catalog_store` writes the fields
+/// to disk as they are.
+#[derive(Default)]
+pub struct TokenIndex {
+    // Sorted so prefixes map to a contiguous range
+    pub(crate) vocab: Vec<String>,
+    pub(crate) postings: Vec<Vec<u32>>,
+    pub(crate) trigrams: HashMap<String, Vec<u32>>,
+    // Number of words in every entry
+    pub(crate) token_counts: Vec<u16>,
+}
+
+impl TokenIndex {
+    pub fn build<'a>(titles: impl Iterator<Item = &'a str>) -> Self {
+        let mut words: HashMap<String, Vec<u32>> = HashMap::new();
+        let mut token_counts = Vec::new();
+
+        for (i, title) in titles.enumerate() {
+            let mut tokens = tokenize(title);
+            token_counts.push(tokens.len().min(u16::MAX as usize) as u16);
+            tokens.sort();
+            tokens.dedup();
+            for token in tokens {
+                words.entry(token).or_default().push(i as u32);
+            }
+        }
+
+        let mut vocab: Vec<(String, Vec<u32>)> = words.into_iter().collect();
+        vocab.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut trigram_index: HashMap<String, Vec<u32>> = HashMap::new();
+        for (id, (word, _)) in vocab.iter().enumerate() {
+            for trigram in trigrams(word) {
+                trigram_index.entry(trigram).or_default().push(id as u32);
+            }
+        }
+
+        let (vocab, postings) = vocab.into_iter().unzip();
+        Self {
+            vocab,
+            postings,
+            trigrams: trigram_index,
+            token_counts,
+        }
+    }
+
+    pub fn token_count(&self, entry: usize) -> usize {
+        self.token_counts.get(entry).copied().unwrap_or(0) as usize
+    }
+
+    /// Vocabulary words a query word can stand for, with a similarity between
+    /// 0 and 1: exact words score 1.0, completions of a prefix and words one
+    /// or two typos away score less.
+    fn expand(&self, word: &str) -> HashMap<u32, f64> {
+        let mut matches: HashMap<u32, f64> = HashMap::new();
+        let mut add = |id: u32, score: f64| {
+            let best = matches.entry(id).or_insert(0.0);
+            if score > *best {
+                *best = score;
+            }
+        };
+
+        // Exact word and prefix completions
+        let start = self.vocab.partition_point(|w| w.as_str() < word);
+        for (offset, candidate) in self.vocab[start..]
+            .iter()
+            .take_while(|w| w.starts_with(word))
+            .take(MAX_PREFIX_EXPANSIONS)
+            .enumerate()
+        {
+            let id = (start + offset) as u32;
+            if candidate == word {
+                add(id, 1.0);
+            } else if word.chars().count() >= 2 {
+                let coverage = word.len() as f64 / candidate.len() as f64;
+                add(id, 0.6 + 0.3 * coverage);
+            }
+        }
+
+        // Typos, only for words long enough to tell apart
+        let len = word.chars().count();
+        if len >= 4 {
+            let max_distance = if len >= 8 { 2 } else { 1 };
+            let query_trigrams = trigrams(word);
+            let mut shared: HashMap<u32, usize> = HashMap::new();
+            for trigram in &query_trigrams {
+                if let Some(ids) = self.trigrams.get(trigram) {
+                    for &id in ids {
+                        *shared.entry(id).or_insert(0) += 1;
+                    }
+                }
+            }
+
+            // A typo destroys at most four trigrams (a transposition)
+            let min_shared = query_trigrams.len().saturating_sub(4 * max_distance).max(1);
+            for (id, count) in shared {
+                if count < min_shared {
+                    continue;
+                }
+                let candidate = &self.vocab[id as usize];
+                let distance = damerau_levenshtein(word, candidate);
+                if distance > 0 && distance <= max_distance {
+                    add(id, 0.8 - 0.15 * (distance - 1) as f64);
+                }
+            }
+        }
+
+        matches
+    }
+
+    /// Score every entry that matches at least one query word. The score is
+    /// the average similarity of the query words (coverage) blended with the
+    /// share of the entry's words that were matched (precision).
+    pub fn score(&self, query: &str) -> HashMap<u32, f64> {
+        let words = tokenize(query);
+        if words.is_empty() {
+            return HashMap::new();
+        }
+
+        // Sum of the best similarity per query word, and matched word count
+        let mut totals: HashMap<u32, (f64, usize)> = HashMap::new();
+        for word in &words {
+            let mut best: HashMap<u32, f64> = HashMap::new();
+            for (id, similarity) in self.expand(word) {
+                for &entry in &self.postings[id as usize] {
+                    let current = best.entry(entry).or_insert(0.0);
+                    if similarity > *current {
+                        *current = similarity;
+                    }
+                }
+            }
+            for (entry, similarity) in best {
+                let total = totals.entry(entry).or_insert((0.0, 0));
+                total.0 += similarity;
+                total.1 += 1;
+            }
+        }
+
+        totals
+            .into_iter()
+            .map(|(entry, (similarity, matched))| {
+                let coverage = similarity / words.len() as f64;
+                let precision = matched as f64 / self.token_count(entry as usize).max(1) as f64;
+                (entry, 0.75 * coverage + 0.25 * precision.min(1.0))
+            })
+            .collect()
+    }
+}
@@ -1,14 +1,15 @@
 use anyhow::Context;
 use librqbit::{
-    api::LiveStats, AddTorrent, AddTorrentOptions, ManagedTorrent, Session, SessionOptions,
-    TorrentStats,
+    api::LiveStats, AddTorrent, AddTorrentOptions, AddTorrentResponse, ManagedTorrent, Session,
+    SessionOptions, TorrentStats, TorrentStatsState,
 };
+use serde::Serialize;
 use std::{
     borrow::Cow,
     cell::RefCell,
     collections::HashMap,
     fs,
-    io::Cursor,
+    io::{Cursor, Read},
     path::PathBuf,
     sync::Arc,
     time::{Duration, Instant},
@@ -17,6 +18,7 @@ use tauri::async_runtime::spawn_blocking;
 use tokio::sync::{Mutex, RwLock};
 use unrar::Archive;
 
+use crate::manifest::{load_manifest, save_manifest};
 use crate::DownloadProgress;
 
 #[derive(Debug, Clone)]
@@ -110,58 +112,11 @@ impl TorrentManager {
 
         log::debug!("[TORRENT] Torrent info stored in manager");
 
-        // Spawn monitoring task using Arc for shared manager
-        let manager = self.clone();
-        let handle_clone = handle.clone();
-        let game_title = game_title.to_string();
-
-        tokio::spawn(async move {
-            log::info!("[TORRENT] Starting monitoring task for ID: {}", id);
-            let mut interval = tokio::time::interval(Duration::from_secs(1));
-            loop {
-                interval.tick().await;
-                let stats = handle_clone.stats();
-
-                if stats.finished {
-                    let game_dir = manager.download_dir.join(&game_title);
-
-                    // Mark extraction as started
-                    {
-                        let mut torrents = manager.torrents.write().await;
-                        if let Some(info) = torrents.get_mut(&(id as u64)) {
-                            info.extraction_started = true;
-                            info.state = DownloadState::Extracting;
-                        }
-                    }
-
-                    // Extract archives with progress tracking
-                    match manager
-                        .extract_archives_in_directory(&game_dir, id as u64)
-                        .await
-                    {
-                        Ok(_) => {
-                            let mut torrents = manager.torrents.write().await;
-                            if let Some(info) = torrents.get_mut(&(id as u64)) {
-                                info.extracted = true;
-                                info.extract_progress = 1.0;
-                                info.extraction_error = None;
-                                info.state = DownloadState::Completed;
-                            }
-                        }
-                        Err(e) => {
-                            eprintln!("Failed to extract archives for {}: {}", game_title, e);
-                            let mut torrents = manager.torrents.write().await;
-                            if let Some(info) = torrents.get_mut(&(id as u64)) {
-                                info.extraction_error = Some(e.to_string());
-                                info.state = DownloadState::Failed;
-                            }
-                        }
-                    }
+        self.record_torrent_source(game_title, torrent_url);
 
-                    break;
-                }
-            }
-        });
+        // Spawn monitoring task using Arc for shared manager
+        self.clone()
+            .spawn_monitor(handle.clone(), id as u64, game_title);
 
         Ok(handle)
     }
@@ -206,16 +161,26 @@ impl TorrentManager {
             },
         );
 
+        self.record_torrent_source(game_title, torrent_path);
+
         // Spawn monitoring task using Arc for shared manager
-        let manager = self.clone();
-        let handle_clone = handle.clone();
+        self.clone()
+            .spawn_monitor(handle.clone(), id as u64, game_title);
+
+        Ok(handle)
+    }
+
+    /// Watches a torrent until it finishes and then extracts its archives
+    fn spawn_monitor(self: Arc<Self>, handle: Arc<ManagedTorrent>, id: u64, game_title: &str) {
+        let manager = self;
         let game_title = game_title.to_string();
 
         tokio::spawn(async move {
+            log::info!("[TORRENT] Starting monitoring task for ID: {}", id);
             let mut interval = tokio::time::interval(Duration::from_secs(1));
             loop {
                 interval.tick().await;
-                let stats = handle_clone.stats();
+                let stats = handle.stats();
 
                 if stats.finished {
                     let game_dir = manager.download_dir.join(&game_title);
@@ -223,20 +188,17 @@ impl TorrentManager {
                     // Mark extraction as started
                     {
                         let mut torrents = manager.torrents.write().await;
-                        if let Some(info) = torrents.get_mut(&(id as u64)) {
+                        if let Some(info) = torrents.get_mut(&id) {
                             info.extraction_started = true;
                             info.state = DownloadState::Extracting;
                         }
                     }
 
                     // Extract archives with progress tracking
-                    match manager
-                        .extract_archives_in_directory(&game_dir, id as u64)
-                        .await
-                    {
+                    match manager.extract_archives_in_directory(&game_dir, id).await {
                         Ok(_) => {
                             let mut torrents = manager.torrents.write().await;
-                            if let Some(info) = torrents.get_mut(&(id as u64)) {
+                            if let Some(info) = torrents.get_mut(&id) {
                                 info.extracted = true;
                                 info.extract_progress = 1.0;
                                 info.extraction_error = None;
@@ -246,7 +208,7 @@ impl TorrentManager {
                         Err(e) => {
                             eprintln!("Failed to extract archives for {}: {}", game_title, e);
                             let mut torrents = manager.torrents.write().await;
-                            if let Some(info) = torrents.get_mut(&(id as u64)) {
+                            if let Some(info) = torrents.get_mut(&id) {
                                 info.extraction_error = Some(e.to_string());
                                 info.state = DownloadState::Failed;
                             }
@@ -257,8 +219,238 @@ impl TorrentManager {
                 }
             }
         });
+    }
 
-        Ok(handle)
+    // Remember where a game came from so its files can be verified later
+    fn record_torrent_source(&self, game_title: &str, source: &str) {
+        let game_dir = self.download_dir.join(game_title);
+        let mut manifest = load_manifest(&game_dir).unwrap_or_default();
+        manifest.title = game_title.to_string();
+        manifest.torrent_source = Some(source.to_string());
+        if let Err(e) = save_manifest(&game_dir, &manifest) {
+            log::warn!(
+                "[TORRENT] Failed to write manifest for {}: {}",
+                game_title,
+                e
+            );
+        }
+    }
+
+    /// Re-hashes the downloaded data of a game and checks the extracted files
+    /// against the CRCs stored in its archives.
+    ///
+    /// When `repair` is set and broken pieces were found, the torrent is resumed
+    /// so that only those pieces are downloaded again.
+    pub async fn verify_game(
+        self: Arc<Self>,
+        game_title: &str,
+        repair: bool,
+    ) -> anyhow::Result<VerifyReport> {
+        let game_dir = self.download_dir.join(game_title);
+        if !game_dir.is_dir() {
+            anyhow::bail!("Game folder does not exist: {}", game_dir.display());
+        }
+
+        let mut report = VerifyReport {
+            game_title: game_title.to_string(),
+            ..Default::default()
+        };
+
+        if let Some(source) = load_manifest(&game_dir).and_then(|m| m.torrent_source) {
+            self.clone()
+                .verify_torrent_data(&source, game_title, repair, &mut report)
+                .await?;
+        } else {
+            log::info!(
+                "[VERIFY] No torrent source recorded for {}, skipping piece check",
+                game_title
+            );
+        }
+
+        self.verify_extracted_files(&game_dir, &mut report).await?;
+
+        Ok(report)
+    }
+
+    async fn verify_torrent_data(
+        self: Arc<Self>,
+        source: &str,
+        game_title: &str,
+        repair: bool,
+        report: &mut VerifyReport,
+    ) -> anyhow::Result<()> {
+        let add_torrent = if source.starts_with("magnet:") {
+            AddTorrent::Url(Cow::Owned(source.to_string()))
+        } else {
+            let bytes = tokio::fs::read(source)
+                .await
+                .with_context(|| format!("Failed to read torrent file {}", source))?;
+            AddTorrent::TorrentFileBytes(bytes.into())
+        };
+
+        // Adding the torrent paused with `overwrite` makes librqbit hash the
+        // existing files instead of starting from scratch
+        let response = self
+            .session
+            .add_torrent(
+                add_torrent,
+                Some(AddTorrentOptions {
+                    paused: true,
+                    overwrite: true,
+                    ..Default::default()
+                }),
+            )
+            .await?;
+
+        let (id, handle, already_managed) = match response {
+            AddTorrentResponse::Added(id, handle) => (id, handle, false),
+            AddTorrentResponse::AlreadyManaged(id, handle) => (id, handle, true),
+            AddTorrentResponse::ListOnly(_) => {
+                anyhow::bail!("Torrent was added as ListOnly and cannot be verified.")
+            }
+        };
+
+        // Wait for the initial hash check to finish
+        loop {
+            let stats = handle.stats();
+            match stats.state {
+                TorrentStatsState::Initializing => {
+                    tokio::time::sleep(Duration::from_millis(500)).await
+                }
+                TorrentStatsState::Error => {
+                    anyhow::bail!(
+                        "Torrent check failed: {}",
+                        stats.error.unwrap_or_else(|| "unknown error".to_string())
+                    )
+                }
+                _ => break,
+            }
+        }
+
+        let stats = handle.stats();
+        let files = handle.with_metadata(|meta| {
+            meta.file_infos
+                .iter()
+                .map(|f| (f.relative_filename.clone(), f.len))
+                .collect::<Vec<_>>()
+        })?;
+
+        let game_dir = self.download_dir.join(game_title);
+        for (i, (relative_path, len)) in files.iter().enumerate() {
+            let have = stats.file_progress.get(i).copied().unwrap_or(0);
+            if have >= *len {
+                continue;
+            }
+            let display_path = relative_path.to_string_lossy().to_string();
+            if game_dir.join(relative_path).exists() {
+                report.modified_files.push(display_path);
+            } else {
+                report.missing_files.push(display_path);
+            }
+        }
+
+        report.torrent_checked = true;
+        report.broken_bytes = stats.total_bytes.saturating_sub(stats.progress_bytes);
+
+        if repair && report.broken_bytes > 0 {
+            log::info!(
+                "[VERIFY] Re-downloading {} broken bytes for {}",
+                report.broken_bytes,
+                game_title
+            );
+            if !already_managed {
+                self.torrents.write().await.insert(
+                    id as u64,
+                    TorrentInfo {
+                        game_title: game_title.to_string(),
+                        torrent_path: source.to_string(),
+                        added_at: Instant::now(),
+                        extracted: false,
+                        extraction_started: false,
+                        extract_progress: 0.0,
+                        extraction_error: None,
+                        state: DownloadState::Downloading,
+                    },
+                );
+                self.clone()
+                    .spawn_monitor(handle.clone(), id as u64, game_title);
+            }
+            self.session.unpause(&handle).await?;
+            report.repair_started = true;
+        } else if !already_managed {
+            // Only drop the torrent from the session, never the files
+            self.session
+                .delete(librqbit::api::TorrentIdOrHash::Id(id), false)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    // Compare the extracted files with the CRCs stored in the RAR headers
+    async fn verify_extracted_files(
+        &self,
+        game_dir: &PathBuf,
+        report: &mut VerifyReport,
+    ) -> anyhow::Result<()> {
+        let mut archives = Vec::new();
+        for entry in fs::read_dir(game_dir)? {
+            let path = entry?.path();
+            let is_rar = path
+                .extension()
+                .and_then(|s| s.to_str())
+                .map(|ext| ext.eq_ignore_ascii_case("rar"))
+                .unwrap_or(false);
+            if path.is_file() && is_rar {
+                archives.push(path);
+            }
+        }
+
+        let destination = game_dir.join("Extracted");
+        let (checked, missing, modified) = spawn_blocking(move || {
+            let mut missing = Vec::new();
+            let mut modified = Vec::new();
+
+            for archive_path in &archives {
+                let archive = Archive::with_password(archive_path, "online-fix.me")
+                    .open_for_listing()
+                    .with_context(|| format!("Failed to open {}", archive_path.display()))?;
+
+                for header in archive {
+                    let header = header?;
+                    if !header.is_file() {
+                        continue;
+                    }
+                    // Same layout as `extract_rar`: the top-level folder is dropped
+                    let relative_path = header.filename.iter().skip(1).collect::<PathBuf>();
+                    let out_path = destination.join(&relative_path);
+                    let display_path = PathBuf::from("Extracted")
+                        .join(&relative_path)
+                        .to_string_lossy()
+                        .to_string();
+
+                    match file_crc32(&out_path) {
+                        Ok((crc, size)) => {
+                            if size != header.unpacked_size || crc != header.file_crc {
+                                modified.push(display_path);
+                            }
+                        }
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                            missing.push(display_path)
+                        }
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+            }
+
+            Ok::<_, anyhow::Error>((archives.len(), missing, modified))
+        })
+        .await??;
+
+        report.archives_checked = checked;
+        report.missing_files.extend(missing);
+        report.modified_files.extend(modified);
+        Ok(())
     }
 
     pub async fn remove_torrent(&self, id: u64) -> anyhow::Result<()> {
@@ -405,6 +597,35 @@ impl TorrentManager {
     }
 }
 
+/// Result of `TorrentManager::verify_game`, paths are relative to the game folder
+#[derive(Debug, Default, Serialize)]
+pub struct VerifyReport {
+    pub game_title: String,
+    pub torrent_checked: bool,
+    pub archives_checked: usize,
+    pub missing_files: Vec<String>,
+    pub modified_files: Vec<String>,
+    pub broken_bytes: u64,
+    pub repair_started: bool,
+}
+
+// CRC32 and size of a file, read in chunks to keep memory usage flat
+fn file_crc32(path: &PathBuf) -> std::io::Result<(u32, u64)> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buffer = vec![0u8; 1024 * 1024];
+    let mut size = 0u64;
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+    Ok((hasher.finalize(), size))
+}
+
 fn torrent_stats_to_progress(
     stats: &TorrentStats,
     info: &TorrentInfo,
@@ -20,6 +20,11 @@ type SteamGame = {
   negative: number;
 };
 
+type BrowsePage = {
+  games: SteamGame[];
+  total: number;
+};
+
 type GameCategory = "onlinefix" | "most_recommended" | "best_reviewed";
 
 // Unified game type for display
@@ -33,8 +38,35 @@ type SearchResult = {
   source: string;
 };
 
-function getProxiedImageUrl(originalImageUrl: string): string {
-  return `http://127.0.0.1:3030/proxy?url=${encodeURIComponent(originalImageUrl)}`;
+type ProxyInfo = {
+  port: number;
+  token: string;
+};
+
+// Port and session token of the local image proxy, fetched once it is up
+let proxyInfo: Promise<ProxyInfo> | null = null;
+
+function getProxyInfo(): Promise<ProxyInfo> {
+  if (!proxyInfo) {
+    proxyInfo = invoke<ProxyInfo>("get_proxy_info").catch((error) => {
+      proxyInfo = null;
+      throw error;
+    });
+  }
+  return proxyInfo;
+}
+
+// Grid tiles are small, so the proxy hands out a resized copy
+function getProxiedImageUrl(proxy: ProxyInfo, originalImageUrl: string): string {
+  const params = new URLSearchParams({
+    url: originalImageUrl,
+    token: proxy.token,
+    w: "460",
+    h: "259",
+    fit: "cover",
+    format: "webp",
+  });
+  return `http://127.0.0.1:${proxy.port}/proxy?${params}`;
 }
 
 export default function GameList() {
@@ -175,15 +207,19 @@ export default function GameList() {
             ? "most_recommended"
             : "best_reviewed";
 
-        const fetchedGames = await invoke<SteamGame[]>("fetch_games_index", {
-          category: steamCategory,
-          page: page - 1,
-          pageSize: 20,
-        });
+        const { games: fetchedGames, total } = await invoke<BrowsePage>(
+          "fetch_games_index",
+          {
+            category: steamCategory,
+            page: page - 1,
+            pageSize: 20,
+          },
+        );
 
-        if (fetchedGames.length === 0) {
+        if (page * 20 >= total) {
           setHasMore(false);
-        } else {
+        }
+        if (fetchedGames.length > 0) {
           // Explicit type casting for steam games
           const newGames: DisplayGame[] = fetchedGames.map(
             (game) =>
@@ -422,20 +458,28 @@ function GameCard({
 
   const title = game.type === "onlinefix" ? game.data.title : game.data.name;
 
+  const [proxy, setProxy] = useState<ProxyInfo | null>(null);
+  useEffect(() => {
+    getProxyInfo()
+      .then(setProxy)
+      .catch((error) => console.error("Image proxy unavailable:", error));
+  }, []);
+  const imageSrc = proxy ? getProxiedImageUrl(proxy, imageUrl) : undefined;
+
   return (
     <div className="relative flex flex-col items-center rounded-xl overflow-visible group">
       <div onClick={onClick} className="cursor-pointer w-full relative">
         <div className="relative">
           {/* Glow effect */}
           <img
-            src={getProxiedImageUrl(imageUrl)}
+            src={imageSrc}
             alt={title}
             className="absolute inset-0 scale-125 object-cover rounded-[2rem] blur-2xl opacity-0 group-hover:opacity-100 transition-opacity duration-300"
           />
 
           {/* Main Image */}
           <img
-            src={getProxiedImageUrl(imageUrl)}
+            src={imageSrc}
             alt={title}
             className="relative w-full aspect-[16/9] object-cover rounded-xl transition-transform duration-300"
           />
@@ -4,6 +4,7 @@ import { invoke } from "@tauri-apps/api/core";
 interface InstalledGame {
   name: string;
   path: string;
+  executable: string;
 }
 
 interface GameDetailsData {
@@ -42,6 +43,20 @@ function InstalledGames() {
     }
   };
 
+  // Saves are backed up once the game exits
+  const handlePlay = async (game: InstalledGame) => {
+    try {
+      await invoke("launch_game", {
+        gameName: game.name,
+        gamePath: game.path,
+        executablePath: game.executable,
+      });
+      await invoke("update_recent_games", { name: game.name, path: game.path });
+    } catch (error) {
+      console.error(`Failed to launch game: ${error}`);
+    }
+  };
+
   const handleUninstall = async (path: string) => {
     try {
       // Call the Rust command to uninstall the game
@@ -137,7 +152,13 @@ function InstalledGames() {
                   {game.name}
                 </h2>
               </div>
-              {/* Hover Button */}
+              {/* Hover Buttons */}
+              <button
+                onClick={() => handlePlay(game)}
+                className="absolute bottom-4 left-4 px-4 py-2 bg-green-600 text-white text-sm rounded-lg opacity-0 group-hover:opacity-100 transition-opacity duration-300"
+              >
+                Play
+              </button>
               <button
                 onClick={() => {
                   setGameToUninstall(game.path); // Set the game to uninstall